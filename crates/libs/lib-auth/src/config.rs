@@ -1,16 +1,21 @@
-use lib_utils::envs::{get_env_b64u_as_u8s, get_env_parse};
+use lib_utils::config::{self, ConfigReader, SettingError};
 use std::sync::OnceLock;
 
 pub fn auth_config() -> &'static AuthConfig {
     static INSTANCE: OnceLock<AuthConfig> = OnceLock::new();
 
     INSTANCE.get_or_init(|| {
-        AuthConfig::load_from_env().unwrap_or_else(|ex| {
+        AuthConfig::load().unwrap_or_else(|ex| {
             panic!("FATAL - WHILE LOADING CONF - Cause: {ex:?}")
         })
     })
 }
 
+/// Load the config without keeping it (e.g., for `--check-config`).
+pub fn check_auth_config() -> config::Result<()> {
+    AuthConfig::load().map(|_| ())
+}
+
 #[allow(non_snake_case)]
 pub struct AuthConfig {
     // -- Crypt
@@ -18,16 +23,61 @@ pub struct AuthConfig {
 
     pub TOKEN_KEY: Vec<u8>,
     pub TOKEN_DURATION_SEC: f64,
+    pub LOGIN_CHALLENGE_DURATION_SEC: f64,
+    pub PWD_RESET_DURATION_SEC: f64,
+
+    pub TOTP_KEY: Vec<u8>,
+    pub TOTP_ISSUER: String,
+
+    // -- Pwd Policy (see `pwd::check_pwd_policy`)
+    pub PWD_MIN_LEN: usize,
+    pub PWD_MIN_CHAR_CLASSES: usize,
 }
 
 impl AuthConfig {
-    fn load_from_env() -> lib_utils::envs::Result<AuthConfig> {
-        Ok(AuthConfig {
+    fn load() -> config::Result<AuthConfig> {
+        let mut reader = ConfigReader::new(&[
+            ("SERVICE_TOKEN_DURATION_SEC", "1800"),
+            ("SERVICE_LOGIN_CHALLENGE_DURATION_SEC", "300"),
+            ("SERVICE_PWD_RESET_DURATION_SEC", "1800"),
+            ("SERVICE_TOTP_ISSUER", "AwesomeApp"),
+            ("SERVICE_PWD_MIN_LEN", "10"),
+            ("SERVICE_PWD_MIN_CHAR_CLASSES", "3"),
+        ]);
+
+        let config = AuthConfig {
             // -- Crypt
-            PWD_KEY: get_env_b64u_as_u8s("SERVICE_PWD_KEY")?,
+            PWD_KEY: reader.get_b64u("SERVICE_PWD_KEY"),
 
-            TOKEN_KEY: get_env_b64u_as_u8s("SERVICE_TOKEN_KEY")?,
-            TOKEN_DURATION_SEC: get_env_parse("SERVICE_TOKEN_DURATION_SEC")?,
-        })
+            TOKEN_KEY: reader.get_b64u("SERVICE_TOKEN_KEY"),
+            TOKEN_DURATION_SEC: reader.get("SERVICE_TOKEN_DURATION_SEC"),
+            LOGIN_CHALLENGE_DURATION_SEC: reader
+                .get("SERVICE_LOGIN_CHALLENGE_DURATION_SEC"),
+            PWD_RESET_DURATION_SEC: reader.get("SERVICE_PWD_RESET_DURATION_SEC"),
+
+            TOTP_KEY: reader.get_b64u("SERVICE_TOTP_KEY"),
+            TOTP_ISSUER: reader.get("SERVICE_TOTP_ISSUER"),
+
+            // -- Pwd Policy
+            PWD_MIN_LEN: reader.get("SERVICE_PWD_MIN_LEN"),
+            PWD_MIN_CHAR_CLASSES: reader.get("SERVICE_PWD_MIN_CHAR_CLASSES"),
+        };
+
+        // Out of the lower, upper, digit, and other char classes.
+        if config.PWD_MIN_CHAR_CLASSES > 4 {
+            reader.push_error(SettingError::WrongFormat(
+                "SERVICE_PWD_MIN_CHAR_CLASSES".to_string(),
+            ));
+        }
+
+        // The totp secrets are encrypted with AES-256-GCM.
+        if !config.TOTP_KEY.is_empty() && config.TOTP_KEY.len() != 32 {
+            reader.push_error(SettingError::WrongFormat(
+                "SERVICE_TOTP_KEY".to_string(),
+            ));
+        }
+
+        reader.finish()?;
+        Ok(config)
     }
 }
@@ -1,5 +1,8 @@
 mod config;
+pub mod oidc;
 pub mod pwd;
 pub mod token;
+pub mod totp;
 
+pub use config::check_auth_config;
 use config::auth_config;
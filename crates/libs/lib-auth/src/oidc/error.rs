@@ -0,0 +1,39 @@
+use serde::Serialize;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+    // -- Flow
+    FlowInvalidFormat,
+
+    // -- Id Token
+    IdTokenHeaderInvalid,
+    IdTokenAlgNotAllowed {
+        alg: String,
+    },
+    /// The signing key is not (or no longer) in the jwks, which may have to be
+    /// fetched again (i.e., key rotation).
+    IdTokenKeyNotFound {
+        kid: Option<String>,
+    },
+    IdTokenKeyInvalid,
+    IdTokenInvalid {
+        cause: String,
+    },
+    IdTokenNonceNotMatching,
+    IdTokenAzpNotMatching,
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(
+        &self,
+        fmt: &mut core::fmt::Formatter,
+    ) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
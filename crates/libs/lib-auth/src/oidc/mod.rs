@@ -0,0 +1,351 @@
+//! OpenID Connect login (authorization code flow with PKCE), the parts which
+//! do not depend on the http client:
+//!
+//! - The per login secrets (`OidcFlow`), kept by the browser between the
+//!   authorization request and the callback.
+//! - The id token validation, against the provider jwks.
+//!
+//! The discovery, jwks and token requests are made by the caller (e.g., the
+//! web-server `oidc` module).
+//!
+
+// region:    --- Modules
+
+mod error;
+
+pub use self::error::{Error, Result};
+
+pub use jsonwebtoken::jwk::JwkSet;
+
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use lib_utils::b64::b64u_encode;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fmt::Display;
+use std::str::FromStr;
+
+// endregion: --- Modules
+
+const FLOW_SECRET_LEN: usize = 32; // 43 chars in base64url (PKCE minimum).
+
+/// Asymmetric algorithms only, since the keys are the provider public ones
+/// (i.e., no `HS*`, for which the public key would be the secret).
+const ALLOWED_ALGS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+// region:    --- Flow
+
+/// The per login secrets, generated for the authorization request, and checked
+/// on the callback.
+///
+/// String format: `state.nonce.pkce_verifier` (all base64url)
+#[derive(Debug)]
+#[cfg_attr(test, derive(PartialEq))]
+pub struct OidcFlow {
+    pub state: String, // Callback csrf check.
+    pub nonce: String, // Id token replay check.
+    pub pkce_verifier: String,
+}
+
+impl OidcFlow {
+    pub fn generate() -> Self {
+        OidcFlow {
+            state: generate_flow_secret(),
+            nonce: generate_flow_secret(),
+            pkce_verifier: generate_flow_secret(),
+        }
+    }
+
+    /// The PKCE `S256` code challenge (RFC 7636), of the authorization request.
+    pub fn pkce_challenge(&self) -> String {
+        b64u_encode(Sha256::digest(self.pkce_verifier.as_bytes()))
+    }
+}
+
+impl FromStr for OidcFlow {
+    type Err = Error;
+
+    fn from_str(flow_str: &str) -> std::result::Result<Self, Self::Err> {
+        let splits: Vec<&str> = flow_str.split('.').collect();
+        let [state, nonce, pkce_verifier] = splits[..] else {
+            return Err(Error::FlowInvalidFormat);
+        };
+        if [state, nonce, pkce_verifier].iter().any(|s| s.is_empty()) {
+            return Err(Error::FlowInvalidFormat);
+        }
+
+        Ok(Self {
+            state: state.to_string(),
+            nonce: nonce.to_string(),
+            pkce_verifier: pkce_verifier.to_string(),
+        })
+    }
+}
+
+impl Display for OidcFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.state, self.nonce, self.pkce_verifier)
+    }
+}
+
+fn generate_flow_secret() -> String {
+    let mut secret = [0; FLOW_SECRET_LEN];
+    OsRng.fill_bytes(&mut secret);
+    b64u_encode(secret)
+}
+
+// endregion: --- Flow
+
+// region:    --- Id Token Validation
+
+/// The id token claims used for the login (the others are ignored).
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub nonce: Option<String>,
+    pub azp: Option<String>,
+
+    // -- profile (when in the scopes)
+    pub preferred_username: Option<String>,
+    pub email: Option<String>,
+    #[serde(default)]
+    pub email_verified: bool,
+}
+
+/// Validate the id token signature (with the jwks key of its `kid`), and its
+/// `iss`, `aud`, `exp`, and `nonce` claims.
+pub fn validate_id_token(
+    id_token: &str,
+    jwks: &JwkSet,
+    issuer: &str,
+    client_id: &str,
+    nonce: &str,
+) -> Result<IdTokenClaims> {
+    let header = decode_header(id_token).map_err(|_| Error::IdTokenHeaderInvalid)?;
+
+    // -- Get the signing key.
+    if !ALLOWED_ALGS.contains(&header.alg) {
+        return Err(Error::IdTokenAlgNotAllowed {
+            alg: format!("{:?}", header.alg),
+        });
+    }
+    let jwk = match &header.kid {
+        Some(kid) => jwks.find(kid),
+        // Note: Without kid, only when the jwks has a single key.
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }
+    .ok_or_else(|| Error::IdTokenKeyNotFound {
+        kid: header.kid.clone(),
+    })?;
+    let key = DecodingKey::from_jwk(jwk).map_err(|_| Error::IdTokenKeyInvalid)?;
+
+    // -- Validate the signature and claims.
+    let mut validation = Validation::new(header.alg);
+    validation.set_issuer(&[issuer]);
+    validation.set_audience(&[client_id]);
+    validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+    let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+        .map_err(|ex| Error::IdTokenInvalid {
+            cause: ex.to_string(),
+        })?
+        .claims;
+
+    if claims.nonce.as_deref() != Some(nonce) {
+        return Err(Error::IdTokenNonceNotMatching);
+    }
+    // Note: For the id tokens with multiple audiences (OIDC Core 3.1.3.7).
+    if claims.azp.as_deref().is_some_and(|azp| azp != client_id) {
+        return Err(Error::IdTokenAzpNotMatching);
+    }
+
+    Ok(claims)
+}
+
+// endregion: --- Id Token Validation
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use lib_utils::time::now_utc;
+    use serde_json::{json, Value};
+
+    const FX_ISSUER: &str = "https://issuer.example.com";
+    const FX_CLIENT_ID: &str = "awesome-app";
+    const FX_KID: &str = "test-key-01";
+
+    /// Local issuer, signing its id tokens with the test key of its jwks.
+    struct MockIssuer {
+        key: EncodingKey,
+        jwks: JwkSet,
+    }
+
+    impl MockIssuer {
+        fn new() -> Result<Self> {
+            Ok(MockIssuer {
+                key: EncodingKey::from_rsa_pem(include_bytes!(
+                    "../../data/test-oidc-key.pem"
+                ))?,
+                jwks: serde_json::from_str(include_str!(
+                    "../../data/test-oidc-jwks.json"
+                ))?,
+            })
+        }
+
+        fn id_token(&self, kid: &str, claims: &Value) -> Result<String> {
+            let mut header = Header::new(Algorithm::RS256);
+            header.kid = Some(kid.to_string());
+            Ok(encode(&header, claims, &self.key)?)
+        }
+    }
+
+    fn fx_claims(nonce: &str) -> Value {
+        json!({
+            "iss": FX_ISSUER,
+            "sub": "fx-subject-01",
+            "aud": FX_CLIENT_ID,
+            "exp": now_utc().unix_timestamp() + 60,
+            "nonce": nonce,
+            "email": "fx-user@example.com",
+            "email_verified": true,
+        })
+    }
+
+    #[test]
+    fn test_validate_id_token_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let issuer = MockIssuer::new()?;
+        let fx_flow = OidcFlow::generate();
+        let fx_id_token = issuer.id_token(FX_KID, &fx_claims(&fx_flow.nonce))?;
+
+        // -- Exec
+        let claims = validate_id_token(
+            &fx_id_token,
+            &issuer.jwks,
+            FX_ISSUER,
+            FX_CLIENT_ID,
+            &fx_flow.nonce,
+        )?;
+
+        // -- Check
+        assert_eq!(claims.sub, "fx-subject-01");
+        assert_eq!(claims.email.as_deref(), Some("fx-user@example.com"));
+        assert!(claims.email_verified);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_id_token_err_nonce_and_audience() -> Result<()> {
+        // -- Setup & Fixtures
+        let issuer = MockIssuer::new()?;
+        let fx_id_token = issuer.id_token(FX_KID, &fx_claims("fx-nonce-01"))?;
+        let validate = |client_id: &str, nonce: &str| {
+            validate_id_token(
+                &fx_id_token,
+                &issuer.jwks,
+                FX_ISSUER,
+                client_id,
+                nonce,
+            )
+        };
+
+        // -- Exec
+        let res_nonce = validate(FX_CLIENT_ID, "fx-nonce-02");
+        let res_aud = validate("other-app", "fx-nonce-01");
+
+        // -- Check
+        assert!(
+            matches!(res_nonce, Err(Error::IdTokenNonceNotMatching)),
+            "Should have matched `Err(Error::IdTokenNonceNotMatching)` but was `{res_nonce:?}`"
+        );
+        assert!(
+            matches!(res_aud, Err(Error::IdTokenInvalid { .. })),
+            "Should have matched `Err(Error::IdTokenInvalid)` but was `{res_aud:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_id_token_err_key() -> Result<()> {
+        // -- Setup & Fixtures
+        let issuer = MockIssuer::new()?;
+        let fx_claims = fx_claims("fx-nonce-01");
+        let fx_unknown_kid_token = issuer.id_token("test-key-02", &fx_claims)?;
+        // Signed with the public key as hmac secret (i.e., alg confusion).
+        let fx_hs256_token = encode(
+            &Header {
+                kid: Some(FX_KID.to_string()),
+                ..Header::new(Algorithm::HS256)
+            },
+            &fx_claims,
+            &EncodingKey::from_secret(FX_KID.as_bytes()),
+        )?;
+        let validate = |id_token: &str| {
+            validate_id_token(
+                id_token,
+                &issuer.jwks,
+                FX_ISSUER,
+                FX_CLIENT_ID,
+                "fx-nonce-01",
+            )
+        };
+
+        // -- Exec
+        let res_kid = validate(&fx_unknown_kid_token);
+        let res_alg = validate(&fx_hs256_token);
+
+        // -- Check
+        assert!(
+            matches!(res_kid, Err(Error::IdTokenKeyNotFound { .. })),
+            "Should have matched `Err(Error::IdTokenKeyNotFound)` but was `{res_kid:?}`"
+        );
+        assert!(
+            matches!(res_alg, Err(Error::IdTokenAlgNotAllowed { .. })),
+            "Should have matched `Err(Error::IdTokenAlgNotAllowed)` but was `{res_alg:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flow_pkce_challenge_rfc7636_ok() -> Result<()> {
+        // -- Fixtures
+        // RFC 7636 Appendix B.
+        let fx_flow = OidcFlow {
+            state: "fx-state".to_string(),
+            nonce: "fx-nonce".to_string(),
+            pkce_verifier: "dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk".to_string(),
+        };
+
+        // -- Exec
+        let flow: OidcFlow = fx_flow.to_string().parse()?;
+
+        // -- Check
+        assert_eq!(flow, fx_flow);
+        assert_eq!(
+            flow.pkce_challenge(),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
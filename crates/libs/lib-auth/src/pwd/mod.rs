@@ -2,8 +2,10 @@
 
 mod error;
 mod hmac_hasher;
+mod policy;
 
 pub use self::error::{Error, Result};
+pub use self::policy::check_pwd_policy;
 
 use crate::auth_config;
 use crate::pwd::hmac_hasher::hmac_sha512_hash;
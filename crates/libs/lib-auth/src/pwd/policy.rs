@@ -0,0 +1,149 @@
+//! Password policy, checked wherever a password is set
+//! (e.g., registration, change, and reset).
+//!
+//! - At least `PWD_MIN_LEN` chars (and at most `PWD_MAX_LEN`, bounding the
+//!   hash cost).
+//! - At least `PWD_MIN_CHAR_CLASSES` of the lower, upper, digit, and other chars.
+//! - Not in the bundled common passwords list (`data/common-pwds.txt`).
+//! - Not the same as the username.
+//!
+//! Note: The comparisons with the common passwords and the username are case
+//!       insensitive.
+
+use crate::auth_config;
+use lib_utils::validate::Violation;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+const PWD_MAX_LEN: usize = 256;
+
+const COMMON_PWDS: &str = include_str!("../../data/common-pwds.txt");
+
+/// Returns all of the policy violations of the password
+/// (i.e., empty when the password meets the policy).
+pub fn check_pwd_policy(pwd_clear: &str, username: &str) -> Vec<Violation> {
+    let config = auth_config();
+    let mut violations = Vec::new();
+
+    // -- Length.
+    let len = pwd_clear.chars().count();
+    if len < config.PWD_MIN_LEN {
+        violations.push(Violation::TooShort {
+            min: config.PWD_MIN_LEN,
+            actual: len,
+        });
+    }
+    if len > PWD_MAX_LEN {
+        violations.push(Violation::TooLong {
+            max: PWD_MAX_LEN,
+            actual: len,
+        });
+    }
+
+    // -- Character classes.
+    let char_classes = char_classes_count(pwd_clear);
+    if char_classes < config.PWD_MIN_CHAR_CLASSES {
+        violations.push(Violation::CharClassesTooFew {
+            min: config.PWD_MIN_CHAR_CLASSES,
+            actual: char_classes,
+        });
+    }
+
+    // -- Common passwords and username.
+    let pwd_lower = pwd_clear.to_lowercase();
+    if common_pwds().contains(pwd_lower.as_str()) {
+        violations.push(Violation::TooCommon);
+    }
+    if pwd_lower == username.to_lowercase() {
+        violations.push(Violation::SameAs {
+            field: "username".to_string(),
+        });
+    }
+
+    violations
+}
+
+/// Number of the lower, upper, digit, and other char classes in the password.
+fn char_classes_count(pwd_clear: &str) -> usize {
+    let has_class =
+        |is_class: fn(&char) -> bool| pwd_clear.chars().any(|c| is_class(&c));
+
+    [
+        has_class(|c| c.is_lowercase()),
+        has_class(|c| c.is_uppercase()),
+        has_class(|c| c.is_ascii_digit()),
+        has_class(|c| !c.is_alphanumeric()),
+    ]
+    .into_iter()
+    .filter(|has| *has)
+    .count()
+}
+
+fn common_pwds() -> &'static HashSet<&'static str> {
+    static INSTANCE: OnceLock<HashSet<&'static str>> = OnceLock::new();
+
+    INSTANCE.get_or_init(|| {
+        COMMON_PWDS
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .collect()
+    })
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_check_pwd_policy_ok() -> Result<()> {
+        // -- Fixtures
+        let fx_pwd = "Correct-horse-7";
+
+        // -- Exec
+        let violations = check_pwd_policy(fx_pwd, "demo1");
+
+        // -- Check
+        assert!(
+            violations.is_empty(),
+            "Should be empty but was `{violations:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_pwd_policy_err_all() -> Result<()> {
+        // -- Fixtures
+        let fx_common_pwd = "Password";
+        let fx_username_pwd = "DEMO1";
+
+        // -- Exec
+        let common_violations = check_pwd_policy(fx_common_pwd, "demo1");
+        let username_violations = check_pwd_policy(fx_username_pwd, "demo1");
+
+        // -- Check
+        assert!(
+            matches!(
+                common_violations.as_slice(),
+                [
+                    Violation::TooShort { actual: 8, .. },
+                    Violation::CharClassesTooFew { actual: 2, .. },
+                    Violation::TooCommon,
+                ]
+            ),
+            "Should have been too short, few and common but was `{common_violations:?}`"
+        );
+        assert!(
+            username_violations.iter().any(
+                |v| matches!(v, Violation::SameAs { field } if field == "username")
+            ),
+            "Should have been same as username but was `{username_violations:?}`"
+        );
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
@@ -69,6 +69,11 @@ pub fn generate_web_token(user: &str, salt: Uuid) -> Result<Token> {
     _generate_token(user, config.TOKEN_DURATION_SEC, salt, &config.TOKEN_KEY)
 }
 
+/// Web token validity, in seconds (e.g., for the token cookie max-age).
+pub fn web_token_duration_sec() -> f64 {
+    auth_config().TOKEN_DURATION_SEC
+}
+
 pub fn validate_web_token(origin_token: &Token, salt: Uuid) -> Result<()> {
     let config = &auth_config();
     _validate_token_sign_and_exp(origin_token, salt, &config.TOKEN_KEY)?;
@@ -78,8 +83,121 @@ pub fn validate_web_token(origin_token: &Token, salt: Uuid) -> Result<()> {
 
 // endregion: --- Web Token Gen and Validation
 
+// region:    --- Impersonation Token Gen and Validation
+
+// Note: The impersonation token (of an admin acting as another user) is a web
+//       token with the `admin>user` ident (`>` not being a username char), and
+//       signed with a salt derived from both user token salts, so that it
+//       ends with the sessions of either user.
+
+const IMPERSONATION_SEP: char = '>';
+
+pub fn generate_impersonation_token(
+    admin: &str,
+    admin_salt: Uuid,
+    user: &str,
+    user_salt: Uuid,
+) -> Result<Token> {
+    let config = &auth_config();
+    _generate_token(
+        &format!("{admin}{IMPERSONATION_SEP}{user}"),
+        config.TOKEN_DURATION_SEC,
+        impersonation_salt(admin_salt, user_salt),
+        &config.TOKEN_KEY,
+    )
+}
+
+pub fn validate_impersonation_token(
+    origin_token: &Token,
+    admin_salt: Uuid,
+    user_salt: Uuid,
+) -> Result<()> {
+    let config = &auth_config();
+    _validate_token_sign_and_exp(
+        origin_token,
+        impersonation_salt(admin_salt, user_salt),
+        &config.TOKEN_KEY,
+    )?;
+
+    Ok(())
+}
+
+/// The `(admin, user)` of an impersonation token (`None` for the other tokens).
+pub fn impersonation_idents(token: &Token) -> Option<(&str, &str)> {
+    token.ident.split_once(IMPERSONATION_SEP)
+}
+
+fn impersonation_salt(admin_salt: Uuid, user_salt: Uuid) -> Uuid {
+    let purpose = [b"impersonation".as_slice(), user_salt.as_bytes()].concat();
+    derived_salt(admin_salt, &purpose)
+}
+
+// endregion: --- Impersonation Token Gen and Validation
+
+// region:    --- Login Challenge Gen and Validation
+
+// Note: The login challenge (given after the password step of a login with a
+//       second factor) is signed with a salt derived from the user token salt,
+//       so that it cannot be used as a web token (and vice versa).
+
+pub fn generate_login_challenge(user: &str, salt: Uuid) -> Result<Token> {
+    let config = &auth_config();
+    _generate_token(
+        user,
+        config.LOGIN_CHALLENGE_DURATION_SEC,
+        derived_salt(salt, b"login-challenge"),
+        &config.TOKEN_KEY,
+    )
+}
+
+pub fn validate_login_challenge(origin_token: &Token, salt: Uuid) -> Result<()> {
+    let config = &auth_config();
+    _validate_token_sign_and_exp(
+        origin_token,
+        derived_salt(salt, b"login-challenge"),
+        &config.TOKEN_KEY,
+    )?;
+
+    Ok(())
+}
+
+// endregion: --- Login Challenge Gen and Validation
+
+// region:    --- Pwd Reset Token Gen and Validation
+
+// Note: The pwd reset token is single use, since the reset rotates the user
+//       token salt (from which its salt is derived).
+
+pub fn generate_pwd_reset_token(user: &str, salt: Uuid) -> Result<Token> {
+    let config = &auth_config();
+    _generate_token(
+        user,
+        config.PWD_RESET_DURATION_SEC,
+        derived_salt(salt, b"pwd-reset"),
+        &config.TOKEN_KEY,
+    )
+}
+
+pub fn validate_pwd_reset_token(origin_token: &Token, salt: Uuid) -> Result<()> {
+    let config = &auth_config();
+    _validate_token_sign_and_exp(
+        origin_token,
+        derived_salt(salt, b"pwd-reset"),
+        &config.TOKEN_KEY,
+    )?;
+
+    Ok(())
+}
+
+// endregion: --- Pwd Reset Token Gen and Validation
+
 // region:    --- (private) Token Gen and Validation
 
+/// Salt of a token `purpose`, derived from the user token salt.
+fn derived_salt(salt: Uuid, purpose: &[u8]) -> Uuid {
+    Uuid::new_v5(&salt, purpose)
+}
+
 fn _generate_token(
     ident: &str,
     duration_sec: f64,
@@ -241,5 +359,66 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_validate_pwd_reset_token_err_as_web_token() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_user = "user_one";
+        let fx_salt =
+            Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
+        let fx_token = generate_pwd_reset_token(fx_user, fx_salt)?;
+
+        // -- Exec
+        let res_reset = validate_pwd_reset_token(&fx_token, fx_salt);
+        let res_web = validate_web_token(&fx_token, fx_salt);
+
+        // -- Check
+        res_reset?;
+        assert!(
+            matches!(res_web, Err(Error::SignatureNotMatching)),
+            "Should have matched `Err(Error::SignatureNotMatching)` but was `{res_web:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_impersonation_token_ok_and_err_other_salt() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_admin_salt =
+            Uuid::parse_str("f05e8961-d6ad-4086-9e78-a6de065e5453").unwrap();
+        let fx_user_salt =
+            Uuid::parse_str("5d2a9c1e-7b44-4f0e-8f5e-0c6a3b9d2e71").unwrap();
+        let fx_token = generate_impersonation_token(
+            "admin_one",
+            fx_admin_salt,
+            "user_one",
+            fx_user_salt,
+        )?;
+
+        // -- Exec
+        let res =
+            validate_impersonation_token(&fx_token, fx_admin_salt, fx_user_salt);
+        let res_rotated =
+            validate_impersonation_token(&fx_token, fx_admin_salt, Uuid::new_v4());
+        let res_web = validate_web_token(&fx_token, fx_user_salt);
+
+        // -- Check
+        res?;
+        assert_eq!(
+            impersonation_idents(&fx_token),
+            Some(("admin_one", "user_one"))
+        );
+        assert!(
+            matches!(res_rotated, Err(Error::SignatureNotMatching)),
+            "Should have matched `Err(Error::SignatureNotMatching)` but was `{res_rotated:?}`"
+        );
+        assert!(
+            matches!(res_web, Err(Error::SignatureNotMatching)),
+            "Should have matched `Err(Error::SignatureNotMatching)` but was `{res_web:?}`"
+        );
+
+        Ok(())
+    }
 }
 // endregion: --- Tests
@@ -0,0 +1,276 @@
+//! TOTP second factor (RFC 6238), compatible with the common authenticator apps
+//! (HMAC-SHA1, 30 seconds step, 6 digits).
+//!
+//! The totp secrets are stored encrypted (AES-256-GCM with the `TOTP_KEY`), and
+//! the recovery codes are meant to be stored hashed (see `normalize_recovery_code`).
+//!
+
+// region:    --- Modules
+
+mod error;
+
+pub use self::error::{Error, Result};
+
+use crate::auth_config;
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use hmac::{Hmac, Mac};
+use lib_utils::b64::{b64u_decode, b64u_encode};
+use lib_utils::time::now_utc;
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha1::Sha1;
+
+// endregion: --- Modules
+
+const SECRET_LEN: usize = 20; // 160 bits (RFC 4226 recommendation).
+const NONCE_LEN: usize = 12;
+const STEP_SEC: i64 = 30;
+const DIGITS: usize = 6;
+/// Accepted steps before and after the current one (clock drift).
+const STEP_WINDOW: i64 = 1;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+const RECOVERY_CODE_LEN: usize = 10;
+
+const B32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+// region:    --- Secret
+
+/// Generate a new random totp secret.
+pub fn generate_secret() -> Vec<u8> {
+    let mut secret = vec![0; SECRET_LEN];
+    OsRng.fill_bytes(&mut secret);
+    secret
+}
+
+/// Encrypt the secret with the `TOTP_KEY`.
+///
+/// String format: `b64u(nonce | ciphertext)`
+pub fn encrypt_secret(secret: &[u8]) -> Result<String> {
+    let cipher = new_cipher()?;
+
+    let mut nonce = [0; NONCE_LEN];
+    OsRng.fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), secret)
+        .map_err(|_| Error::SecretEncryptFail)?;
+
+    Ok(b64u_encode([nonce.as_slice(), &ciphertext].concat()))
+}
+
+pub fn decrypt_secret(secret_enc: &str) -> Result<Vec<u8>> {
+    let cipher = new_cipher()?;
+
+    let content = b64u_decode(secret_enc).map_err(|_| Error::SecretDecryptFail)?;
+    if content.len() <= NONCE_LEN {
+        return Err(Error::SecretDecryptFail);
+    }
+    let (nonce, ciphertext) = content.split_at(NONCE_LEN);
+
+    cipher
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| Error::SecretDecryptFail)
+}
+
+/// Base32 secret, as entered manually in the authenticator apps.
+pub fn secret_b32(secret: &[u8]) -> String {
+    b32_encode(secret)
+}
+
+/// The `otpauth://` uri for the authenticator apps (usually shown as a QR code).
+pub fn otpauth_uri(account: &str, secret: &[u8]) -> String {
+    let issuer = url_encode(&auth_config().TOTP_ISSUER);
+    let account = url_encode(account);
+
+    format!(
+        "otpauth://totp/{issuer}:{account}?secret={}&issuer={issuer}\
+         &algorithm=SHA1&digits={DIGITS}&period={STEP_SEC}",
+        b32_encode(secret)
+    )
+}
+
+fn new_cipher() -> Result<Aes256Gcm> {
+    Aes256Gcm::new_from_slice(&auth_config().TOTP_KEY).map_err(|_| Error::KeyFail)
+}
+
+// endregion: --- Secret
+
+// region:    --- Code Validation
+
+/// Validate the code against the current time steps (within the drift window),
+/// and returns the matched step.
+///
+/// Note: Only the steps after `last_step` are accepted, so that a code cannot
+///       be replayed (the caller stores the returned step as the new `last_step`).
+pub fn validate_code(secret: &[u8], code: &str, last_step: i64) -> Result<i64> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != DIGITS || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Err(Error::CodeNotMatching);
+    }
+
+    let current_step = now_utc().unix_timestamp() / STEP_SEC;
+
+    for step in (current_step - STEP_WINDOW)..=(current_step + STEP_WINDOW) {
+        if step > last_step && step_code(secret, step)? == code {
+            return Ok(step);
+        }
+    }
+
+    Err(Error::CodeNotMatching)
+}
+
+/// The RFC 4226 (HOTP) code of the time step.
+fn step_code(secret: &[u8], step: i64) -> Result<String> {
+    let mut hmac_sha1 =
+        <Hmac<Sha1> as Mac>::new_from_slice(secret).map_err(|_| Error::KeyFail)?;
+    hmac_sha1.update(&(step as u64).to_be_bytes());
+    let hash = hmac_sha1.finalize().into_bytes();
+
+    // -- Dynamic truncation.
+    let offset = (hash[hash.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([
+        hash[offset] & 0x7f,
+        hash[offset + 1],
+        hash[offset + 2],
+        hash[offset + 3],
+    ]);
+
+    Ok(format!(
+        "{:0width$}",
+        binary % 10u32.pow(DIGITS as u32),
+        width = DIGITS
+    ))
+}
+
+// endregion: --- Code Validation
+
+// region:    --- Recovery Codes
+
+/// Generate new one-time recovery codes (e.g., `abcde-fghij`), to be shown once
+/// to the user.
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let mut bytes = [0; 8];
+            OsRng.fill_bytes(&mut bytes);
+            let code = b32_encode(&bytes).to_lowercase();
+            let (first, second) =
+                code[..RECOVERY_CODE_LEN].split_at(RECOVERY_CODE_LEN / 2);
+            format!("{first}-{second}")
+        })
+        .collect()
+}
+
+/// The recovery code as hashed and matched (without separators and case).
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+// endregion: --- Recovery Codes
+
+// region:    --- Encoding Utils
+
+/// RFC 4648 base32 (without padding).
+fn b32_encode(bytes: &[u8]) -> String {
+    let mut res = String::with_capacity((bytes.len() * 8).div_ceil(5));
+    let mut buffer: u32 = 0;
+    let mut bits = 0;
+
+    for &byte in bytes {
+        buffer = (buffer << 8) | byte as u32;
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            res.push(B32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        res.push(B32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+    }
+
+    res
+}
+
+/// Percent-encode everything but the unreserved characters (RFC 3986).
+fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+// endregion: --- Encoding Utils
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_step_code_rfc6238_ok() -> Result<()> {
+        // -- Fixtures
+        // RFC 6238 Appendix B (SHA1), with the 6 last digits of the 8 digit codes.
+        let fx_secret = b"12345678901234567890";
+        let fx_cases = [
+            (59, "287082"),
+            (1111111109, "081804"),
+            (1234567890, "005924"),
+            (2000000000, "279037"),
+        ];
+
+        // -- Exec & Check
+        for (time, code) in fx_cases {
+            assert_eq!(step_code(fx_secret, time / STEP_SEC)?, code);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_code_err_replay() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_secret = generate_secret();
+        let current_step = now_utc().unix_timestamp() / STEP_SEC;
+        let fx_code = step_code(&fx_secret, current_step)?;
+
+        // -- Exec
+        let step = validate_code(&fx_secret, &fx_code, 0)?;
+        let res = validate_code(&fx_secret, &fx_code, step);
+
+        // -- Check
+        assert_eq!(step, current_step);
+        assert!(
+            matches!(res, Err(Error::CodeNotMatching)),
+            "Should have matched `Err(Error::CodeNotMatching)` but was `{res:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_secret_encrypt_decrypt_ok() -> Result<()> {
+        // -- Fixtures
+        let fx_secret = generate_secret();
+
+        // -- Exec
+        let secret_enc = encrypt_secret(&fx_secret)?;
+        let secret = decrypt_secret(&secret_enc)?;
+
+        // -- Check
+        assert_eq!(secret, fx_secret);
+        assert_eq!(b32_encode(b"foobar"), "MZXW6YTBOI");
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
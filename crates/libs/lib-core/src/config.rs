@@ -1,33 +1,74 @@
-use lib_utils::envs::get_env;
+use lib_utils::config::{self, ConfigReader};
 use std::sync::OnceLock;
 
 pub fn core_config() -> &'static CoreConfig {
     static INSTANCE: OnceLock<CoreConfig> = OnceLock::new();
 
     INSTANCE.get_or_init(|| {
-        CoreConfig::load_from_env().unwrap_or_else(|ex| {
+        CoreConfig::load().unwrap_or_else(|ex| {
             panic!("FATAL - WHILE LOADING CONF - Cause: {ex:?}")
         })
     })
 }
 
+/// Load the config without keeping it (e.g., for `--check-config`).
+pub fn check_core_config() -> config::Result<()> {
+    CoreConfig::load().map(|_| ())
+}
+
 #[allow(non_snake_case)]
 pub struct CoreConfig {
     // -- Db
     pub DB_URL: String,
+    /// Read replica, used for the model reads when set.
+    pub DB_READ_URL: Option<String>,
+    pub DB_MAX_CONNECTIONS: u32,
+    /// Wait for a pool connection before `Error::Timeout`.
+    pub DB_ACQUIRE_TIMEOUT_MS: u64,
+    pub DB_IDLE_TIMEOUT_SEC: u64,
+    /// Postgres `statement_timeout` of the connections (0 for none).
+    pub DB_STATEMENT_TIMEOUT_MS: u64,
+    /// Deadline of a Bmc query, including the connection acquire.
+    pub DB_QUERY_TIMEOUT_MS: u64,
+    /// Connect attempts at startup (with backoff from `DB_CONNECT_BACKOFF_MS`).
+    pub DB_CONNECT_MAX_ATTEMPTS: u32,
+    pub DB_CONNECT_BACKOFF_MS: u64,
 
     // -- Web
+    #[allow(dead_code)] // Not read by lib-core (see the web config).
     pub WEB_FOLDER: String,
 }
 
 impl CoreConfig {
-    fn load_from_env() -> lib_utils::envs::Result<CoreConfig> {
-        Ok(CoreConfig {
+    fn load() -> config::Result<CoreConfig> {
+        let mut reader = ConfigReader::new(&[
+            ("SERVICE_DB_MAX_CONNECTIONS", "5"),
+            ("SERVICE_DB_ACQUIRE_TIMEOUT_MS", "5000"),
+            ("SERVICE_DB_IDLE_TIMEOUT_SEC", "600"),
+            ("SERVICE_DB_STATEMENT_TIMEOUT_MS", "10000"),
+            ("SERVICE_DB_QUERY_TIMEOUT_MS", "15000"),
+            ("SERVICE_DB_CONNECT_MAX_ATTEMPTS", "10"),
+            ("SERVICE_DB_CONNECT_BACKOFF_MS", "500"),
+            ("SERVICE_WEB_FOLDER", "web-folder/"),
+        ]);
+
+        let config = CoreConfig {
             // -- Db
-            DB_URL: get_env("SERVICE_DB_URL")?,
+            DB_URL: reader.get("SERVICE_DB_URL"),
+            DB_READ_URL: reader.get_opt("SERVICE_DB_READ_URL"),
+            DB_MAX_CONNECTIONS: reader.get("SERVICE_DB_MAX_CONNECTIONS"),
+            DB_ACQUIRE_TIMEOUT_MS: reader.get("SERVICE_DB_ACQUIRE_TIMEOUT_MS"),
+            DB_IDLE_TIMEOUT_SEC: reader.get("SERVICE_DB_IDLE_TIMEOUT_SEC"),
+            DB_STATEMENT_TIMEOUT_MS: reader.get("SERVICE_DB_STATEMENT_TIMEOUT_MS"),
+            DB_QUERY_TIMEOUT_MS: reader.get("SERVICE_DB_QUERY_TIMEOUT_MS"),
+            DB_CONNECT_MAX_ATTEMPTS: reader.get("SERVICE_DB_CONNECT_MAX_ATTEMPTS"),
+            DB_CONNECT_BACKOFF_MS: reader.get("SERVICE_DB_CONNECT_BACKOFF_MS"),
 
             // -- Web
-            WEB_FOLDER: get_env("SERVICE_WEB_FOLDER")?,
-        })
+            WEB_FOLDER: reader.get("SERVICE_WEB_FOLDER"),
+        };
+
+        reader.finish()?;
+        Ok(config)
     }
 }
@@ -4,26 +4,84 @@ mod error;
 
 pub use self::error::{Error, Result};
 
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
 // endregion: --- Modules
 
 #[derive(Clone, Debug)]
 pub struct Ctx {
+    /// The effective user (i.e., the impersonated one, if any).
     user_id: i64,
+    /// The real user, when an admin acts as `user_id` (see `with_impersonator`).
+    impersonator_id: Option<i64>,
+    /// For the admin operations (e.g., the user management rpcs).
+    ///
+    /// Note: True for the root ctx.
+    is_admin: bool,
+    /// Id of the request this ctx was created for
+    /// (used in the tracing spans of the model layer).
+    req_id: Option<Uuid>,
+    /// When true, the model reads go to the primary db rather than the
+    /// read replica (set on a write, so that the request reads its writes).
+    ///
+    /// Note: Shared by the clones of the ctx, and reset by `with_req_id`
+    ///       (i.e., one read-your-writes scope per request).
+    read_primary: Arc<AtomicBool>,
 }
 
 // Constructors.
 impl Ctx {
     pub fn root_ctx() -> Self {
-        Ctx { user_id: 0 }
+        Ctx {
+            user_id: 0,
+            impersonator_id: None,
+            is_admin: true,
+            req_id: None,
+            read_primary: Arc::default(),
+        }
     }
 
     pub fn new(user_id: i64) -> Result<Self> {
         if user_id == 0 {
             Err(Error::CtxCannotNewRootCtx)
         } else {
-            Ok(Self { user_id })
+            Ok(Self {
+                user_id,
+                impersonator_id: None,
+                is_admin: false,
+                req_id: None,
+                read_primary: Arc::default(),
+            })
         }
     }
+
+    /// Note: For the user `is_admin` (e.g., as resolved by the web-server auth).
+    pub fn with_admin(mut self, is_admin: bool) -> Self {
+        self.is_admin = is_admin;
+        self
+    }
+
+    /// Note: For the impersonation tokens, `impersonator_id` being the admin
+    ///       acting as the ctx user (e.g., flagged in the request log).
+    pub fn with_impersonator(mut self, impersonator_id: i64) -> Self {
+        self.impersonator_id = Some(impersonator_id);
+        self
+    }
+
+    pub fn with_req_id(mut self, req_id: Uuid) -> Self {
+        self.req_id = Some(req_id);
+        self.read_primary = Arc::default();
+        self
+    }
+
+    /// Force the reads made with this ctx (and its clones) to the primary db
+    /// (e.g., to read a write made by another request).
+    pub fn with_read_primary(mut self) -> Self {
+        self.read_primary = Arc::new(AtomicBool::new(true));
+        self
+    }
 }
 
 // Property Accessors.
@@ -31,4 +89,38 @@ impl Ctx {
     pub fn user_id(&self) -> i64 {
         self.user_id
     }
+
+    pub fn impersonator_id(&self) -> Option<i64> {
+        self.impersonator_id
+    }
+
+    pub fn is_impersonated(&self) -> bool {
+        self.impersonator_id.is_some()
+    }
+
+    /// The user behind the request (the impersonator, if any).
+    pub fn real_user_id(&self) -> i64 {
+        self.impersonator_id.unwrap_or(self.user_id)
+    }
+
+    pub fn is_admin(&self) -> bool {
+        self.is_admin
+    }
+
+    pub fn req_id(&self) -> Option<Uuid> {
+        self.req_id
+    }
+
+    pub fn read_primary(&self) -> bool {
+        self.read_primary.load(Ordering::Relaxed)
+    }
+}
+
+// Model Layer Marks.
+impl Ctx {
+    /// Mark that a write was made with this ctx,
+    /// so that its next reads go to the primary db.
+    pub(crate) fn mark_write(&self) {
+        self.read_primary.store(true, Ordering::Relaxed);
+    }
 }
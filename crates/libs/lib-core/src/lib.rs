@@ -5,4 +5,5 @@ pub mod model;
 // #[cfg(test)] // Commented during early development.
 pub mod _dev_utils;
 
+pub use config::check_core_config;
 use config::core_config;
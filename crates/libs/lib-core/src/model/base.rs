@@ -1,22 +1,51 @@
+use crate::core_config;
 use crate::ctx::Ctx;
+use crate::model::store::acquire;
 use crate::model::ModelManager;
 use crate::model::{Error, Result};
 use modql::field::HasFields;
 use modql::filter::{FilterGroups, ListOptions};
 use modql::SIden;
 use sea_query::{
-    Condition, Expr, Iden, IntoIden, PostgresQueryBuilder, Query, TableRef,
+    Condition, Expr, Iden, IntoIden, PostgresQueryBuilder, Query, SimpleExpr,
+    TableRef,
 };
 use sea_query_binder::SqlxBinder;
 use sqlx::postgres::PgRow;
 use sqlx::FromRow;
+use std::future::Future;
+use std::time::Duration;
+use tracing::instrument;
 
 const LIST_LIMIT_DEFAULT: i64 = 300;
 const LIST_LIMIT_MAX: i64 = 1000;
 
+/// Postgres `query_canceled` code (e.g., `statement_timeout` reached).
+const PG_QUERY_CANCELED: &str = "57014";
+
 #[derive(Iden)]
 pub enum CommonIden {
     Id,
+    // -- Audit
+    Mid,
+    MimpersonatorId,
+    Mtime,
+}
+
+/// The audit columns of a written row, i.e., the ctx user (`mid`), the admin
+/// acting as this user if any (`mimpersonator_id`), and the write time.
+///
+/// Note: Set by `create` and `update`, and to be added to the Bmc specific
+///       writes (e.g., `UserBmc::set_pwd`).
+pub fn audit_values(ctx: &Ctx) -> [(CommonIden, SimpleExpr); 3] {
+    [
+        (CommonIden::Mid, Expr::value(ctx.user_id())),
+        (
+            CommonIden::MimpersonatorId,
+            Expr::value(ctx.impersonator_id()),
+        ),
+        (CommonIden::Mtime, Expr::cust("now()")),
+    ]
 }
 
 pub trait DbBmc {
@@ -27,6 +56,40 @@ pub trait DbBmc {
     }
 }
 
+// Note: The base functions are instrumented with the entity and the ctx request
+//       id, so that their sql queries are traced under the request span.
+//
+// Note: The reads use `mm.db_read(ctx)` (read replica, when set), and the writes
+//       mark the ctx, so that its next reads go to the primary.
+
+/// Run a Bmc query with the `DB_QUERY_TIMEOUT_MS` deadline
+/// (which includes the pool connection acquire, see `store::acquire`).
+///
+/// Note: The pool acquire timeout and the postgres `statement_timeout`
+///       are returned as `Error::Timeout` as well.
+pub async fn with_query_timeout<T>(
+    query: impl Future<Output = sqlx::Result<T>>,
+) -> Result<T> {
+    let timeout = Duration::from_millis(core_config().DB_QUERY_TIMEOUT_MS);
+
+    match tokio::time::timeout(timeout, query).await {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(ex)) if is_sqlx_timeout(&ex) => Err(Error::Timeout),
+        Ok(Err(ex)) => Err(ex.into()),
+        Err(_) => Err(Error::Timeout),
+    }
+}
+
+fn is_sqlx_timeout(ex: &sqlx::Error) -> bool {
+    match ex {
+        sqlx::Error::PoolTimedOut => true,
+        sqlx::Error::Database(db_ex) => {
+            db_ex.code().as_deref() == Some(PG_QUERY_CANCELED)
+        }
+        _ => false,
+    }
+}
+
 pub fn finalize_list_options(
     list_options: Option<ListOptions>,
 ) -> Result<ListOptions> {
@@ -57,7 +120,12 @@ pub fn finalize_list_options(
     }
 }
 
-pub async fn create<MC, E>(_ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(entity = MC::TABLE, req_id = ?ctx.req_id())
+)]
+pub async fn create<MC, E>(ctx: &Ctx, mm: &ModelManager, data: E) -> Result<i64>
 where
     MC: DbBmc,
     E: HasFields,
@@ -66,7 +134,11 @@ where
 
     // -- Prep data
     let fields = data.not_none_fields();
-    let (columns, sea_values) = fields.for_sea_insert();
+    let (mut columns, mut sea_values) = fields.for_sea_insert();
+    for (column, value) in audit_values(ctx) {
+        columns.push(column.into_iden());
+        sea_values.push(value);
+    }
 
     // -- Build query
     let mut query = Query::insert();
@@ -78,20 +150,30 @@ where
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let (id,) = sqlx::query_as_with::<_, (i64,), _>(&sql, values)
-        .fetch_one(db)
-        .await?;
+    let (id,) = with_query_timeout(async {
+        let mut conn = acquire(db).await?;
+        sqlx::query_as_with::<_, (i64,), _>(&sql, values)
+            .fetch_one(&mut *conn)
+            .await
+    })
+    .await?;
+    ctx.mark_write();
 
     Ok(id)
 }
 
-pub async fn get<MC, E>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(entity = MC::TABLE, id = id, req_id = ?ctx.req_id())
+)]
+pub async fn get<MC, E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
 where
     MC: DbBmc,
     E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
     E: HasFields,
 {
-    let db = mm.db();
+    let db = mm.db_read(ctx);
 
     // -- Build query
     let mut query = Query::select();
@@ -102,19 +184,28 @@ where
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let entity = sqlx::query_as_with::<_, E, _>(&sql, values)
-        .fetch_optional(db)
-        .await?
-        .ok_or(Error::EntityNotFound {
-            entity: MC::TABLE,
-            id,
-        })?;
+    let entity = with_query_timeout(async {
+        let mut conn = acquire(db).await?;
+        sqlx::query_as_with::<_, E, _>(&sql, values)
+            .fetch_optional(&mut *conn)
+            .await
+    })
+    .await?
+    .ok_or(Error::EntityNotFound {
+        entity: MC::TABLE,
+        id,
+    })?;
 
     Ok(entity)
 }
 
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(entity = MC::TABLE, req_id = ?ctx.req_id())
+)]
 pub async fn list<MC, E, F>(
-    _ctx: &Ctx,
+    ctx: &Ctx,
     mm: &ModelManager,
     filters: Option<F>,
     list_options: Option<ListOptions>,
@@ -125,7 +216,7 @@ where
     E: for<'r> FromRow<'r, PgRow> + Unpin + Send,
     E: HasFields,
 {
-    let db = mm.db();
+    let db = mm.db_read(ctx);
 
     // -- Build query
     let mut query = Query::select();
@@ -144,15 +235,24 @@ where
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let entities = sqlx::query_as_with::<_, E, _>(&sql, values)
-        .fetch_all(db)
-        .await?;
+    let entities = with_query_timeout(async {
+        let mut conn = acquire(db).await?;
+        sqlx::query_as_with::<_, E, _>(&sql, values)
+            .fetch_all(&mut *conn)
+            .await
+    })
+    .await?;
 
     Ok(entities)
 }
 
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(entity = MC::TABLE, id = id, req_id = ?ctx.req_id())
+)]
 pub async fn update<MC, E>(
-    _ctx: &Ctx,
+    ctx: &Ctx,
     mm: &ModelManager,
     id: i64,
     data: E,
@@ -165,7 +265,11 @@ where
 
     // -- Prep data
     let fields = data.not_none_fields();
-    let fields = fields.for_sea_update();
+    let fields = fields.for_sea_update().chain(
+        audit_values(ctx)
+            .into_iter()
+            .map(|(column, value)| (column.into_iden(), value)),
+    );
 
     // -- Build query
     let mut query = Query::update();
@@ -176,10 +280,12 @@ where
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let count = sqlx::query_with(&sql, values)
-        .execute(db)
-        .await?
-        .rows_affected();
+    let count = with_query_timeout(async {
+        let mut conn = acquire(db).await?;
+        sqlx::query_with(&sql, values).execute(&mut *conn).await
+    })
+    .await?
+    .rows_affected();
 
     // -- Check result
     if count == 0 {
@@ -188,11 +294,17 @@ where
             id,
         })
     } else {
+        ctx.mark_write();
         Ok(())
     }
 }
 
-pub async fn delete<MC>(_ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
+#[instrument(
+    level = "debug",
+    skip_all,
+    fields(entity = MC::TABLE, id = id, req_id = ?ctx.req_id())
+)]
+pub async fn delete<MC>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()>
 where
     MC: DbBmc,
 {
@@ -206,10 +318,12 @@ where
 
     // -- Exec query
     let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-    let count = sqlx::query_with(&sql, values)
-        .execute(db)
-        .await?
-        .rows_affected();
+    let count = with_query_timeout(async {
+        let mut conn = acquire(db).await?;
+        sqlx::query_with(&sql, values).execute(&mut *conn).await
+    })
+    .await?
+    .rows_affected();
 
     // -- Check result
     if count == 0 {
@@ -218,6 +332,7 @@ where
             id,
         })
     } else {
+        ctx.mark_write();
         Ok(())
     }
 }
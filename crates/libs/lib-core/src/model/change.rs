@@ -0,0 +1,176 @@
+//! Model change feed.
+//!
+//! - Bmcs publish a `ModelChange` after each create/update/delete.
+//! - Changes are broadcast in-process, and sent with Postgres `NOTIFY` on the
+//!   `model_change` channel, so that the other instances get them as well.
+//! - The `LISTEN` side is started on the first `subscribe`, and skips the
+//!   changes published by this instance (already broadcast in-process).
+//!
+
+use crate::core_config;
+use crate::ctx::Ctx;
+use crate::model::store::Db;
+use crate::model::ModelManager;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::postgres::PgListener;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+const PG_CHANNEL: &str = "model_change";
+const BROADCAST_CAPACITY: usize = 256;
+const LISTEN_RETRY_DELAY: Duration = Duration::from_secs(5);
+
+// region:    --- Types
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModelChange {
+    /// The Bmc table name (e.g., `task`).
+    pub entity: String,
+    pub id: i64,
+    pub kind: ChangeKind,
+    /// New state of the entity (`None` when deleted).
+    pub data: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// The `NOTIFY` payload.
+#[derive(Serialize, Deserialize)]
+struct PgChangePayload {
+    origin: Uuid,
+    change: ModelChange,
+}
+
+// endregion: --- Types
+
+// region:    --- ChangeHub
+
+#[derive(Clone)]
+pub(in crate::model) struct ChangeHub {
+    inner: Arc<ChangeHubInner>,
+}
+
+struct ChangeHubInner {
+    /// Identifies this instance in the `NOTIFY` payloads.
+    origin: Uuid,
+    tx: broadcast::Sender<ModelChange>,
+    listen_once: Once,
+}
+
+impl ChangeHub {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(BROADCAST_CAPACITY);
+
+        ChangeHub {
+            inner: Arc::new(ChangeHubInner {
+                origin: Uuid::new_v4(),
+                tx,
+                listen_once: Once::new(),
+            }),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ModelChange> {
+        let inner = self.inner.clone();
+        self.inner.listen_once.call_once(|| {
+            tokio::spawn(listen_pg_changes(inner));
+        });
+
+        self.inner.tx.subscribe()
+    }
+
+    /// Publish the change in-process and to the other instances.
+    ///
+    /// Note: The change is published after the data change has been committed,
+    ///       so a `NOTIFY` failure is logged rather than returned.
+    pub async fn publish(&self, db: &Db, change: ModelChange) {
+        let payload = PgChangePayload {
+            origin: self.inner.origin,
+            change,
+        };
+
+        match serde_json::to_string(&payload) {
+            Ok(payload_json) => {
+                let res = sqlx::query("SELECT pg_notify($1, $2)")
+                    .bind(PG_CHANNEL)
+                    .bind(payload_json)
+                    .execute(db)
+                    .await;
+                if let Err(ex) = res {
+                    warn!("{:<12} - pg_notify fail - {ex}", "MODEL_CHANGE");
+                }
+            }
+            Err(ex) => warn!("{:<12} - payload to json fail - {ex}", "MODEL_CHANGE"),
+        }
+
+        // Note: Only fails when there are no subscribers.
+        let _ = self.inner.tx.send(payload.change);
+    }
+}
+
+/// Forward the changes of the other instances to the in-process broadcast.
+async fn listen_pg_changes(hub: Arc<ChangeHubInner>) {
+    loop {
+        let listener = async {
+            let mut listener = PgListener::connect(&core_config().DB_URL).await?;
+            listener.listen(PG_CHANNEL).await?;
+            Ok::<_, sqlx::Error>(listener)
+        };
+
+        let mut listener = match listener.await {
+            Ok(listener) => listener,
+            Err(ex) => {
+                warn!("{:<12} - listen fail - {ex}", "MODEL_CHANGE");
+                tokio::time::sleep(LISTEN_RETRY_DELAY).await;
+                continue;
+            }
+        };
+
+        // Note: `recv` transparently reconnects when the connection is lost.
+        while let Ok(notification) = listener.recv().await {
+            match serde_json::from_str::<PgChangePayload>(notification.payload()) {
+                Ok(payload) if payload.origin != hub.origin => {
+                    let _ = hub.tx.send(payload.change);
+                }
+                Ok(_) => (),
+                Err(ex) => debug!("{:<12} - bad payload - {ex}", "MODEL_CHANGE"),
+            }
+        }
+
+        tokio::time::sleep(LISTEN_RETRY_DELAY).await;
+    }
+}
+
+// endregion: --- ChangeHub
+
+// region:    --- Bmc Helpers
+
+/// Publish the change of an entity, with its new state when `data` is given.
+pub(in crate::model) async fn publish_change<E: Serialize>(
+    _ctx: &Ctx,
+    mm: &ModelManager,
+    entity: &'static str,
+    id: i64,
+    kind: ChangeKind,
+    data: Option<&E>,
+) {
+    let change = ModelChange {
+        entity: entity.to_string(),
+        id,
+        kind,
+        data: data.and_then(|data| serde_json::to_value(data).ok()),
+    };
+
+    mm.changes().publish(mm.db(), change).await;
+}
+
+// endregion: --- Bmc Helpers
@@ -1,6 +1,7 @@
 use crate::model::store;
 use derive_more::From;
-use lib_auth::pwd;
+use lib_auth::{pwd, totp};
+use lib_utils::validate;
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
 
@@ -17,12 +18,49 @@ pub enum Error {
         max: i64,
         actual: i64,
     },
+    /// Query over the `DB_QUERY_TIMEOUT_MS` (or the pool acquire/statement timeout).
+    Timeout,
+
+    // -- User
+    UserAlreadyExists {
+        username: String,
+    },
+    /// A user was already created for the oidc subject
+    /// (e.g., by a concurrent first login).
+    OidcSubjectAlreadyExists {
+        oidc_subject: String,
+    },
+    PwdNotMatching {
+        user_id: i64,
+    },
+    /// The `token_salt` changed since the token was validated.
+    TokenSaltRotated {
+        user_id: i64,
+    },
+
+    // -- Totp
+    TotpAlreadyEnabled {
+        user_id: i64,
+    },
+    TotpNotEnrolled {
+        user_id: i64,
+    },
+    TotpNotEnabled {
+        user_id: i64,
+    },
+    TotpCodeNotMatching {
+        user_id: i64,
+    },
 
     // -- Modules
     #[from]
     Pwd(pwd::Error),
     #[from]
+    Totp(totp::Error),
+    #[from]
     Store(store::Error),
+    #[from]
+    Validate(validate::Error),
 
     // -- Externals
     #[from]
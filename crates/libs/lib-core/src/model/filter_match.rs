@@ -0,0 +1,74 @@
+//! In-memory matching of the modql op values, for the entity states already
+//! loaded (e.g., the change feed filters, see `TaskFilter::matches`).
+//!
+//! Note: Same semantic as the sql of the list filters, except that:
+//!       - The `contains`, `starts_with`, and `ends_with` values are matched as
+//!         plain text (no `%` or `_` wildcards).
+//!       - The string order is the byte order (not the db collation).
+//!       - `$notEndsWith` is the negation of `$endsWith` (the modql 0.3 sql
+//!         matches it as `$endsWith`).
+
+use modql::filter::{
+    OpValBool, OpValInt64, OpValString, OpValsBool, OpValsInt64, OpValsString,
+};
+
+/// Returns true if the (non null) `value` matches all of the op values.
+pub(in crate::model) fn match_int64(ovs: &OpValsInt64, value: i64) -> bool {
+    ovs.0.iter().all(|ov| match ov {
+        OpValInt64::Eq(v) => value == *v,
+        OpValInt64::Not(v) => value != *v,
+        OpValInt64::In(vs) => vs.contains(&value),
+        OpValInt64::NotIn(vs) => !vs.contains(&value),
+        OpValInt64::Lt(v) => value < *v,
+        OpValInt64::Lte(v) => value <= *v,
+        OpValInt64::Gt(v) => value > *v,
+        OpValInt64::Gte(v) => value >= *v,
+        OpValInt64::Null(null) => !null,
+    })
+}
+
+/// Returns true if the (non null) `value` matches all of the op values.
+pub(in crate::model) fn match_bool(ovs: &OpValsBool, value: bool) -> bool {
+    ovs.0.iter().all(|ov| match ov {
+        OpValBool::Eq(v) => value == *v,
+        OpValBool::Not(v) => value != *v,
+        OpValBool::Null(null) => !null,
+    })
+}
+
+/// Returns true if the (non null) `value` matches all of the op values.
+pub(in crate::model) fn match_string(ovs: &OpValsString, value: &str) -> bool {
+    let any = |vs: &[String], f: &dyn Fn(&str) -> bool| vs.iter().any(|v| f(v));
+
+    ovs.0.iter().all(|ov| match ov {
+        OpValString::Eq(v) => value == v,
+        OpValString::Not(v) => value != v,
+        OpValString::In(vs) => vs.iter().any(|v| v == value),
+        OpValString::NotIn(vs) => !vs.iter().any(|v| v == value),
+        OpValString::Lt(v) => value < v.as_str(),
+        OpValString::Lte(v) => value <= v.as_str(),
+        OpValString::Gt(v) => value > v.as_str(),
+        OpValString::Gte(v) => value >= v.as_str(),
+
+        OpValString::Contains(v) => value.contains(v.as_str()),
+        OpValString::NotContains(v) => !value.contains(v.as_str()),
+        OpValString::ContainsAny(vs) => any(vs, &|v| value.contains(v)),
+        OpValString::NotContainsAny(vs) => any(vs, &|v| !value.contains(v)),
+        OpValString::ContainsAll(vs) => {
+            vs.iter().all(|v| value.contains(v.as_str()))
+        }
+
+        OpValString::StartsWith(v) => value.starts_with(v.as_str()),
+        OpValString::NotStartsWith(v) => !value.starts_with(v.as_str()),
+        OpValString::StartsWithAny(vs) => any(vs, &|v| value.starts_with(v)),
+        OpValString::NotStartsWithAny(vs) => any(vs, &|v| !value.starts_with(v)),
+
+        OpValString::EndsWith(v) => value.ends_with(v.as_str()),
+        OpValString::NotEndsWith(v) => !value.ends_with(v.as_str()),
+        OpValString::EndsWithAny(vs) => any(vs, &|v| value.ends_with(v)),
+        OpValString::NotEndsWithAny(vs) => any(vs, &|v| !value.ends_with(v)),
+
+        OpValString::Empty(empty) => value.is_empty() == *empty,
+        OpValString::Null(null) => !null,
+    })
+}
@@ -8,6 +8,10 @@
 //! - The `ModelManager` holds the internal states/resources
 //!   needed by ModelControllers to access data.
 //!   (e.g., db_pool, S3 client, redis client).
+//! - When a read replica is configured (`SERVICE_DB_READ_URL`), the Bmc reads
+//!   go to it, unless the `Ctx` made a write or asked for the primary
+//!   (see `Ctx::with_read_primary`). Writes always go to the primary,
+//!   and so do the user auth and credential reads (see `user::UserBy`).
 //! - Model Controllers (e.g., `TaskBmc`, `ProjectBmc`) implement
 //!   CRUD and other data access methods on a given "entity"
 //!   (e.g., `Task`, `Project`).
@@ -20,33 +24,121 @@
 // region:    --- Modules
 
 mod base;
+pub mod change;
 mod error;
+mod filter_match;
 mod store;
 pub mod task;
 pub mod user;
 
 pub use self::error::{Error, Result};
+pub use self::store::{set_acquire_wait_observer, DbPoolStats};
 
-use crate::model::store::{new_db_pool, Db};
+use crate::core_config;
+use crate::ctx::Ctx;
+use crate::model::base::DbBmc;
+use crate::model::change::{ChangeHub, ModelChange};
+use crate::model::store::{db_pool_stats, new_db_pool, Db};
+use crate::model::task::TaskBmc;
+use crate::model::user::UserBmc;
+use tokio::sync::broadcast;
 
 // endregion: --- Modules
 
 #[derive(Clone)]
 pub struct ModelManager {
     db: Db,
+    db_read: Option<Db>,
+    changes: ChangeHub,
 }
 
 impl ModelManager {
     /// Constructor
     pub async fn new() -> Result<Self> {
-        let db = new_db_pool().await?;
+        let config = core_config();
+        let db = new_db_pool(&config.DB_URL).await?;
+        let db_read = match &config.DB_READ_URL {
+            Some(db_read_url) => Some(new_db_pool(db_read_url).await?),
+            None => None,
+        };
 
-        Ok(ModelManager { db })
+        Ok(ModelManager {
+            db,
+            db_read,
+            changes: ChangeHub::new(),
+        })
     }
 
-    /// Returns the sqlx db pool reference.
+    /// Close the db pool, waiting for the checked out connections
+    /// to be returned (e.g., on server shutdown).
+    pub async fn close(&self) {
+        self.db.close().await;
+        if let Some(db_read) = &self.db_read {
+            db_read.close().await;
+        }
+    }
+
+    /// Check that a db connection can be acquired and used,
+    /// on the primary and read replica (e.g., for readiness probes).
+    pub async fn ping_db(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.db).await?;
+        if let Some(db_read) = &self.db_read {
+            sqlx::query("SELECT 1").execute(db_read).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the Bmc tables missing from the db schema
+    /// (i.e., the schema scripts not applied yet).
+    pub async fn missing_tables(&self) -> Result<Vec<&'static str>> {
+        let tables = [TaskBmc::TABLE, UserBmc::TABLE];
+
+        let mut missing = Vec::new();
+        for table in tables {
+            let (exists,): (bool,) =
+                sqlx::query_as("SELECT to_regclass(quote_ident($1)) IS NOT NULL")
+                    .bind(table)
+                    .fetch_one(&self.db)
+                    .await?;
+            if !exists {
+                missing.push(table);
+            }
+        }
+
+        Ok(missing)
+    }
+
+    /// Returns the db pool state (e.g., for metrics).
+    pub fn db_pool_stats(&self) -> DbPoolStats {
+        db_pool_stats(&self.db)
+    }
+
+    /// Subscribe to the changes made through the Bmcs
+    /// (by this instance and the other ones).
+    pub fn subscribe_changes(&self) -> broadcast::Receiver<ModelChange> {
+        self.changes.subscribe()
+    }
+
+    /// Returns the sqlx db pool reference (primary, for the writes).
     /// (Only for the model layer)
     pub(in crate::model) fn db(&self) -> &Db {
         &self.db
     }
+
+    /// Returns the sqlx db pool reference for the reads of `ctx`,
+    /// the read replica when set and the ctx does not need the primary.
+    /// (Only for the model layer)
+    pub(in crate::model) fn db_read(&self, ctx: &Ctx) -> &Db {
+        match &self.db_read {
+            Some(db_read) if !ctx.read_primary() => db_read,
+            _ => &self.db,
+        }
+    }
+
+    /// Returns the change hub reference.
+    /// (Only for the model layer)
+    pub(in crate::model) fn changes(&self) -> &ChangeHub {
+        &self.changes
+    }
 }
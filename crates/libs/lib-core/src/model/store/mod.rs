@@ -5,24 +5,146 @@ mod error;
 pub use self::error::{Error, Result};
 
 use crate::core_config;
-use sqlx::postgres::PgPoolOptions;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgConnectOptions, PgPoolOptions};
 use sqlx::{Pool, Postgres};
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use tracing::warn;
 
 // endregion: --- Modules
 
 pub type Db = Pool<Postgres>;
 
-pub async fn new_db_pool() -> Result<Db> {
+const CONNECT_BACKOFF_MAX: Duration = Duration::from_secs(10);
+
+/// Create the db pool for `db_url` (primary or read replica), retrying the
+/// connect with an exponential backoff (e.g., when the db starts after the service).
+pub async fn new_db_pool(db_url: &str) -> Result<Db> {
+    let config = core_config();
+
     // * See NOTE 1) below
-    let max_connections = if cfg!(test) { 1 } else { 5 };
+    let max_connections = if cfg!(test) {
+        1
+    } else {
+        config.DB_MAX_CONNECTIONS
+    };
 
-    PgPoolOptions::new()
+    let connect_options = PgConnectOptions::from_str(db_url)
+        .map_err(|ex| Error::FailToCreatePool(ex.to_string()))?
+        .options([("statement_timeout", config.DB_STATEMENT_TIMEOUT_MS)]);
+    let pool_options = PgPoolOptions::new()
         .max_connections(max_connections)
-        .connect(&core_config().DB_URL)
-        .await
-        .map_err(|ex| Error::FailToCreatePool(ex.to_string()))
+        .acquire_timeout(Duration::from_millis(config.DB_ACQUIRE_TIMEOUT_MS))
+        .idle_timeout(Duration::from_secs(config.DB_IDLE_TIMEOUT_SEC));
+
+    let mut backoff = Duration::from_millis(config.DB_CONNECT_BACKOFF_MS);
+    let mut attempt = 1;
+    loop {
+        match pool_options
+            .clone()
+            .connect_with(connect_options.clone())
+            .await
+        {
+            Ok(db) => return Ok(db),
+            Err(ex) if attempt < config.DB_CONNECT_MAX_ATTEMPTS => {
+                warn!(
+                    "{:<12} - connect attempt {attempt} failed, retry in {backoff:?} - {ex}",
+                    "DB_POOL"
+                );
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(CONNECT_BACKOFF_MAX);
+                attempt += 1;
+            }
+            Err(ex) => return Err(Error::FailToCreatePool(ex.to_string())),
+        }
+    }
+}
+
+// region:    --- Acquire
+
+static ACQUIRE_WAIT_OBSERVER: OnceLock<fn(Duration)> = OnceLock::new();
+
+/// Set the function called with the wait of each pool connection acquire
+/// (e.g., to record it in a metrics histogram). Only the first one is kept.
+pub fn set_acquire_wait_observer(observer: fn(Duration)) {
+    let _ = ACQUIRE_WAIT_OBSERVER.set(observer);
+}
+
+/// Acquire a pool connection, reporting the wait to the acquire wait observer.
+///
+/// Note: Reported for the failed acquires too (e.g., pool timeout), which are
+///       the longest waits.
+pub async fn acquire(db: &Db) -> sqlx::Result<PoolConnection<Postgres>> {
+    let start = Instant::now();
+    let conn = db.acquire().await;
+    if let Some(observer) = ACQUIRE_WAIT_OBSERVER.get() {
+        observer(start.elapsed());
+    }
+
+    conn
+}
+
+// endregion: --- Acquire
+
+/// Snapshot of the db pool counters (e.g., for metrics).
+#[derive(Debug, Clone)]
+pub struct DbPoolStats {
+    pub max: u32,
+    pub size: u32,
+    pub idle: usize,
 }
 
+/// Note: Only reads the pool counters (no connection acquired), so that
+///       the scrapes do not add to the pool load.
+pub fn db_pool_stats(db: &Db) -> DbPoolStats {
+    DbPoolStats {
+        max: db.options().get_max_connections(),
+        size: db.size(),
+        idle: db.num_idle(),
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::_dev_utils;
+    use crate::ctx::Ctx;
+    use crate::model::task::TaskBmc;
+    use anyhow::Result;
+    use serial_test::serial;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static ACQUIRE_WAITS: AtomicUsize = AtomicUsize::new(0);
+
+    fn count_acquire_wait(_wait: Duration) {
+        ACQUIRE_WAITS.fetch_add(1, Ordering::SeqCst);
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_acquire_wait_observed() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        set_acquire_wait_observer(count_acquire_wait);
+        let waits_before = ACQUIRE_WAITS.load(Ordering::SeqCst);
+
+        // -- Exec
+        TaskBmc::list(&ctx, &mm, None, None).await?;
+
+        // -- Check
+        assert_eq!(ACQUIRE_WAITS.load(Ordering::SeqCst), waits_before + 1);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
+
 // NOTE 1) This is not an ideal situation; however, with sqlx 0.7.1, when executing `cargo test`, some tests that use sqlx fail at a
 //         rather low level (in the tokio scheduler). It appears to be a low-level thread/async issue, as removing/adding
 //         tests causes different tests to fail. The cause remains uncertain, but setting max_connections to 1 resolves the issue.
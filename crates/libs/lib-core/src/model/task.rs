@@ -1,16 +1,21 @@
 use crate::ctx::Ctx;
 use crate::model::base::{self, DbBmc};
+use crate::model::change::{self, ChangeKind};
+use crate::model::filter_match::{match_bool, match_int64, match_string};
 use crate::model::ModelManager;
 use crate::model::Result;
+use lazy_regex::regex;
+use lib_utils::validate::{self, StrRule, Validate, Validator};
 use modql::field::Fields;
 use modql::filter::{
     FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString,
 };
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
+use tracing::warn;
 
 // region:    --- Task Types
-#[derive(Debug, Clone, Fields, FromRow, Serialize)]
+#[derive(Debug, Clone, Fields, FromRow, Serialize, Deserialize)]
 pub struct Task {
     pub id: i64,
 
@@ -18,12 +23,12 @@ pub struct Task {
     pub done: bool,
 }
 
-#[derive(Fields, Deserialize)]
+#[derive(Fields, Serialize, Deserialize)]
 pub struct TaskForCreate {
     pub title: String,
 }
 
-#[derive(Fields, Default, Deserialize)]
+#[derive(Fields, Default, Serialize, Deserialize)]
 pub struct TaskForUpdate {
     pub title: Option<String>,
     pub done: Option<bool>,
@@ -36,8 +41,50 @@ pub struct TaskFilter {
     title: Option<OpValsString>,
     done: Option<OpValsBool>,
 }
+
+impl TaskFilter {
+    /// Returns true if the `task` matches all of the filter fields
+    /// (in memory, e.g., for the task change feed).
+    pub fn matches(&self, task: &Task) -> bool {
+        let id = self.id.as_ref();
+        let title = self.title.as_ref();
+        let done = self.done.as_ref();
+
+        id.is_none_or(|ovs| match_int64(ovs, task.id))
+            && title.is_none_or(|ovs| match_string(ovs, &task.title))
+            && done.is_none_or(|ovs| match_bool(ovs, task.done))
+    }
+}
 // endregion: --- Task Types
 
+// region:    --- Task Validation
+/// Note: Max length matches the `task.title varchar(128)` column.
+fn title_rules() -> [StrRule; 3] {
+    [
+        StrRule::NotBlank,
+        StrRule::MaxLen(128),
+        // No control chars (e.g., new lines, tabs).
+        StrRule::Pattern(regex!(r"^\P{Cc}*$")),
+    ]
+}
+
+impl Validate for TaskForCreate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("title", &self.title, &title_rules())
+            .finish()
+    }
+}
+
+impl Validate for TaskForUpdate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("title", &self.title, &title_rules())
+            .finish()
+    }
+}
+// endregion: --- Task Validation
+
 // region:    --- TaskBmc
 pub struct TaskBmc;
 
@@ -51,7 +98,12 @@ impl TaskBmc {
         mm: &ModelManager,
         task_c: TaskForCreate,
     ) -> Result<i64> {
-        base::create::<Self, _>(ctx, mm, task_c).await
+        task_c.validate()?;
+
+        let id = base::create::<Self, _>(ctx, mm, task_c).await?;
+        Self::publish_change(ctx, mm, id, ChangeKind::Created).await;
+
+        Ok(id)
     }
 
     pub async fn get(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<Task> {
@@ -73,11 +125,48 @@ impl TaskBmc {
         id: i64,
         task_u: TaskForUpdate,
     ) -> Result<()> {
-        base::update::<Self, _>(ctx, mm, id, task_u).await
+        task_u.validate()?;
+
+        base::update::<Self, _>(ctx, mm, id, task_u).await?;
+        Self::publish_change(ctx, mm, id, ChangeKind::Updated).await;
+
+        Ok(())
     }
 
     pub async fn delete(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<()> {
-        base::delete::<Self>(ctx, mm, id).await
+        base::delete::<Self>(ctx, mm, id).await?;
+        Self::publish_change(ctx, mm, id, ChangeKind::Deleted).await;
+
+        Ok(())
+    }
+
+    /// Publish the task change, with the new task state
+    /// (for create and update).
+    ///
+    /// Note: As for the publish itself, a failure (e.g., of the new state read)
+    ///       is logged rather than returned, the task change being committed.
+    async fn publish_change(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        kind: ChangeKind,
+    ) {
+        let task = match kind {
+            ChangeKind::Deleted => None,
+            ChangeKind::Created | ChangeKind::Updated => {
+                match Self::get(ctx, mm, id).await {
+                    Ok(task) => Some(task),
+                    Err(ex) => {
+                        warn!(
+                            "{:<12} - task read fail, not published - {ex}",
+                            "MODEL_CHANGE"
+                        );
+                        return;
+                    }
+                }
+            }
+        };
+        change::publish_change(ctx, mm, Self::TABLE, id, kind, task.as_ref()).await;
     }
 }
 // endregion: --- TaskBmc
@@ -116,6 +205,31 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_create_err_validate() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_title = "a".repeat(129);
+
+        // -- Exec
+        let task_c = TaskForCreate { title: fx_title };
+        let res = TaskBmc::create(&ctx, &mm, task_c).await;
+
+        // -- Check
+        assert!(
+            matches!(
+                &res,
+                Err(Error::Validate(validate::Error::FieldsInvalid(field_errors)))
+                    if field_errors.len() == 1 && field_errors[0].field == "title"
+            ),
+            "Should have been a `title` validation error but was `{res:?}`"
+        );
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_get_err_not_found() -> Result<()> {
@@ -226,6 +340,36 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_filter_matches_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_task = Task {
+            id: 1000,
+            title: "test_filter_matches_ok-task 01.a".to_string(),
+            done: false,
+        };
+        let fx_filters: Vec<TaskFilter> = serde_json::from_value(json!([
+			{
+				"title": {"$endsWith": ".a", "$containsAny": ["01", "02"]},
+				"done": false
+			},
+			{"title": {"$endsWith": ".a"}, "done": true},
+			{"id": {"$gte": 1001}},
+			{"title": {"$notEndsWith": ".a"}},
+			{"title": {"$in": ["test_filter_matches_ok-task 01.a"]}, "id": 1000},
+			{"title": {"$empty": true}}
+		]))?;
+
+        // -- Exec
+        let matches: Vec<bool> =
+            fx_filters.iter().map(|f| f.matches(&fx_task)).collect();
+
+        // -- Check
+        assert_eq!(matches, [true, false, false, false, true, false]);
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_update_ok() -> Result<()> {
@@ -257,6 +401,86 @@ mod tests {
         Ok(())
     }
 
+    #[serial]
+    #[tokio::test]
+    async fn test_update_ok_audit_impersonated() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let fx_title = "test_update_ok_audit - task 01";
+        let fx_task = _dev_utils::seed_tasks(&Ctx::root_ctx(), &mm, &[fx_title])
+            .await?
+            .remove(0);
+        let fx_user_id = 1000;
+        let fx_admin_id = 1001;
+        let ctx = Ctx::new(fx_user_id)?.with_impersonator(fx_admin_id);
+
+        // -- Exec
+        TaskBmc::update(
+            &ctx,
+            &mm,
+            fx_task.id,
+            TaskForUpdate {
+                done: Some(true),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // -- Check
+        let (mid, mimpersonator_id): (Option<i64>, Option<i64>) =
+            sqlx::query_as("SELECT mid, mimpersonator_id FROM task WHERE id = $1")
+                .bind(fx_task.id)
+                .fetch_one(mm.db())
+                .await?;
+        assert_eq!(mid, Some(fx_user_id));
+        assert_eq!(mimpersonator_id, Some(fx_admin_id));
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_changes_published_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_title = "test_changes_published_ok - task 01";
+        let mut changes = mm.subscribe_changes();
+
+        // -- Exec
+        let task_c = TaskForCreate {
+            title: fx_title.to_string(),
+        };
+        let id = TaskBmc::create(&ctx, &mm, task_c).await?;
+        let task_u = TaskForUpdate {
+            done: Some(true),
+            ..Default::default()
+        };
+        TaskBmc::update(&ctx, &mm, id, task_u).await?;
+        TaskBmc::delete(&ctx, &mm, id).await?;
+
+        // -- Check
+        let mut task_changes = Vec::new();
+        while task_changes.len() < 3 {
+            let change = changes.recv().await?;
+            if change.entity == "task" && change.id == id {
+                task_changes.push(change);
+            }
+        }
+        let kinds: Vec<ChangeKind> = task_changes.iter().map(|c| c.kind).collect();
+        assert_eq!(
+            kinds,
+            [ChangeKind::Created, ChangeKind::Updated, ChangeKind::Deleted]
+        );
+        assert_eq!(
+            task_changes[1].data.as_ref().and_then(|d| d.get("done")),
+            Some(&json!(true))
+        );
+        assert!(task_changes[2].data.is_none());
+
+        Ok(())
+    }
+
     #[serial]
     #[tokio::test]
     async fn test_delete_err_not_found() -> Result<()> {
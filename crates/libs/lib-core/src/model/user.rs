@@ -1,21 +1,51 @@
 use crate::ctx::Ctx;
 use crate::model::base::{self, DbBmc};
+use crate::model::store::acquire;
 use crate::model::ModelManager;
-use crate::model::Result;
+use crate::model::{Error, Result};
+use lazy_regex::regex;
 use lib_auth::pwd::{self, ContentToHash};
+use lib_auth::totp;
+use lib_utils::validate::{self, StrRule, Validate, Validator};
 use modql::field::{Fields, HasFields};
+use modql::filter::{
+    FilterNodes, ListOptions, OpValsBool, OpValsInt64, OpValsString,
+};
 use sea_query::{Expr, Iden, PostgresQueryBuilder, Query, SimpleExpr};
 use sea_query_binder::SqlxBinder;
 use serde::{Deserialize, Serialize};
 use sqlx::postgres::PgRow;
-use sqlx::FromRow;
+use sqlx::{Connection, FromRow};
 use uuid::Uuid;
 
 // region:    --- User Types
-#[derive(Clone, Fields, FromRow, Debug, Serialize)]
+#[derive(Clone, Fields, FromRow, Debug, Serialize, Deserialize)]
 pub struct User {
     pub id: i64,
     pub username: String,
+    pub email: Option<String>,
+
+    // -- access
+    pub is_admin: bool,
+    pub disabled: bool,
+}
+
+/// The profile of the ctx user (e.g., `whoami`), the `display_name`, `email`,
+/// `timezone`, and `locale` being editable by the user.
+#[derive(Clone, Fields, FromRow, Debug, Serialize, Deserialize)]
+pub struct UserProfile {
+    pub id: i64,
+    pub username: String,
+    pub display_name: Option<String>,
+    pub email: Option<String>,
+    pub timezone: Option<String>,
+    pub locale: Option<String>,
+
+    // -- access
+    pub is_admin: bool,
+
+    // -- second factor
+    pub totp_enabled: bool,
 }
 
 #[derive(Deserialize)]
@@ -29,6 +59,53 @@ pub struct UserForInsert {
     pub username: String,
 }
 
+/// Note: The `username` change ends the user sessions (the web tokens being
+///       for the username).
+#[derive(Fields, Default, Serialize, Deserialize)]
+pub struct UserForUpdate {
+    pub username: Option<String>,
+    pub email: Option<String>,
+}
+
+/// Note: The `username` is not part of the profile (see `UserForUpdate`),
+///       nor is the `email` (see `UserBmc::change_email`).
+#[derive(Fields, Default, Serialize, Deserialize)]
+pub struct UserForProfileUpdate {
+    pub display_name: Option<String>,
+    pub timezone: Option<String>,
+    pub locale: Option<String>,
+}
+
+#[derive(Fields)]
+struct UserForEmailUpdate {
+    email: String,
+}
+
+#[derive(Fields)]
+struct UserForDisable {
+    disabled: bool,
+}
+
+#[derive(FilterNodes, Deserialize, Default, Debug)]
+pub struct UserFilter {
+    id: Option<OpValsInt64>,
+
+    username: Option<OpValsString>,
+    email: Option<OpValsString>,
+    is_admin: Option<OpValsBool>,
+    disabled: Option<OpValsBool>,
+}
+
+/// The user of a first oidc login (without password), linked to its external
+/// subject.
+#[derive(Fields)]
+pub struct UserForOidcCreate {
+    pub username: String,
+    pub email: Option<String>, // only when verified by the provider
+    pub oidc_issuer: String,
+    pub oidc_subject: String,
+}
+
 #[derive(Clone, FromRow, Fields, Debug)]
 pub struct UserForLogin {
     pub id: i64,
@@ -38,6 +115,12 @@ pub struct UserForLogin {
     pub pwd: Option<String>, // hashed with #_scheme_id_#....
     pub pwd_salt: Uuid,
     pub token_salt: Uuid,
+
+    // -- second factor
+    pub totp_enabled: bool,
+
+    // -- access
+    pub disabled: bool,
 }
 
 #[derive(Clone, FromRow, Fields, Debug)]
@@ -47,23 +130,198 @@ pub struct UserForAuth {
 
     // -- token info
     pub token_salt: Uuid,
+
+    // -- access
+    pub is_admin: bool,
+    pub disabled: bool,
+    /// The user this (admin) user acts as, if any (see `set_impersonating`).
+    pub impersonating_user_id: Option<i64>,
+}
+
+#[derive(Clone, FromRow, Fields, Debug)]
+pub struct UserForPwdReset {
+    pub id: i64,
+    pub username: String,
+    pub email: Option<String>, // the reset mail recipient
+
+    // -- token info
+    pub token_salt: Uuid,
+}
+
+#[derive(Clone, FromRow, Fields, Debug)]
+pub struct UserForTotp {
+    pub id: i64,
+    pub username: String,
+    pub pwd_salt: Uuid,
+
+    // -- totp info
+    pub totp_secret_enc: Option<String>, // encrypted with the `TOTP_KEY`
+    pub totp_enabled: bool,
+    pub totp_last_step: i64, // last accepted time step (no replay)
+    pub totp_recovery_codes: Option<String>, // hashed like pwd, space separated
+}
+
+#[derive(Default, Fields)]
+struct UserForTotpUpdate {
+    totp_secret_enc: Option<String>,
+    totp_enabled: Option<bool>,
+    totp_last_step: Option<i64>,
+    totp_recovery_codes: Option<String>,
+}
+
+/// Given on `totp_enroll`, for the user to add the secret to its
+/// authenticator app.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TotpEnrollment {
+    pub secret: String, // base32, for manual entry
+    pub otpauth_uri: String,
+}
+
+// region:    --- User Validation
+/// Note: Max length matches the `user.email varchar(256)` column.
+fn email_rules() -> [StrRule; 2] {
+    [
+        StrRule::MaxLen(256),
+        StrRule::Pattern(regex!(r"^[^@\s]+@[^@\s]+$")),
+    ]
+}
+
+/// Note: Lower case only, so that usernames are not confusable
+///       (the `user.username varchar(128)` column is case sensitive).
+fn username_rules() -> [StrRule; 3] {
+    [
+        StrRule::MinLen(3),
+        StrRule::MaxLen(64),
+        // Starts with a letter, then letters, digits, `.`, `_` or `-`.
+        StrRule::Pattern(regex!(r"^[a-z][a-z0-9._-]*$")),
+    ]
+}
+
+/// IANA time zone name (e.g., `Europe/Paris`, `Etc/GMT+5`, or `UTC`).
+///
+/// Note: Only the format is checked, the names being resolved by the clients.
+fn timezone_rules() -> [StrRule; 2] {
+    [
+        StrRule::MaxLen(64),
+        StrRule::Pattern(regex!(r"^[A-Za-z][A-Za-z0-9_+-]*(/[A-Za-z0-9_+-]+)*$")),
+    ]
 }
 
+/// BCP 47 language tag (e.g., `en`, `en-US`, or `zh-Hant-TW`).
+fn locale_rules() -> [StrRule; 2] {
+    [
+        StrRule::MaxLen(35),
+        StrRule::Pattern(regex!(r"^[a-zA-Z]{2,3}(-[a-zA-Z0-9]{1,8})*$")),
+    ]
+}
+
+impl Validate for UserForCreate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("username", &self.username, &username_rules())
+            .add(
+                "pwd_clear",
+                pwd::check_pwd_policy(&self.pwd_clear, &self.username),
+            )
+            .finish()
+    }
+}
+
+impl Validate for UserForUpdate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("username", &self.username, &username_rules())
+            .check("email", &self.email, &email_rules())
+            .finish()
+    }
+}
+
+impl Validate for UserForProfileUpdate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check(
+                "display_name",
+                &self.display_name,
+                &[StrRule::NotBlank, StrRule::MaxLen(128)],
+            )
+            .check("timezone", &self.timezone, &timezone_rules())
+            .check("locale", &self.locale, &locale_rules())
+            .finish()
+    }
+}
+
+impl Validate for UserForEmailUpdate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("email", &self.email, &email_rules())
+            .finish()
+    }
+}
+
+impl Validate for UserForOidcCreate {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("username", &self.username, &username_rules())
+            .finish()
+    }
+}
+
+/// The `lib_auth` pwd policy, for the passwords set after the creation
+/// (see `set_pwd`).
+fn validate_pwd_clear(pwd_clear: &str, username: &str) -> validate::Result<()> {
+    Validator::new()
+        .add("pwd_clear", pwd::check_pwd_policy(pwd_clear, username))
+        .finish()
+}
+// endregion: --- User Validation
+
 /// Marker trait
-pub trait UserBy: HasFields + for<'r> FromRow<'r, PgRow> + Unpin + Send {}
+pub trait UserBy: HasFields + for<'r> FromRow<'r, PgRow> + Unpin + Send {
+    /// True for the auth and credential data (e.g., `token_salt`, `disabled`),
+    /// which is always read from the primary db, a lagging read replica
+    /// letting a rotated token or a disabled user through.
+    const READ_PRIMARY: bool = false;
+}
 
 impl UserBy for User {}
-impl UserBy for UserForLogin {}
-impl UserBy for UserForAuth {}
+impl UserBy for UserProfile {}
+impl UserBy for UserForLogin {
+    const READ_PRIMARY: bool = true;
+}
+impl UserBy for UserForAuth {
+    const READ_PRIMARY: bool = true;
+}
+impl UserBy for UserForPwdReset {
+    const READ_PRIMARY: bool = true;
+}
+impl UserBy for UserForTotp {
+    const READ_PRIMARY: bool = true;
+}
+
+/// The ctx for the `E` reads (see `UserBy::READ_PRIMARY`).
+fn read_ctx<E: UserBy>(ctx: &Ctx) -> Ctx {
+    if E::READ_PRIMARY {
+        ctx.clone().with_read_primary()
+    } else {
+        ctx.clone()
+    }
+}
 
 // Note: Since the entity properties Iden will be given by modql::field::Fields
 //       UserIden does not have to be exhaustive, but just have the columns
 //       we use in our specific code.
-#[derive(Iden)]
+#[derive(Iden, Clone, Copy)]
 enum UserIden {
     Id,
     Username,
     Pwd,
+    PwdSalt,
+    TokenSalt,
+    TotpLastStep,
+    TotpRecoveryCodes,
+    OidcIssuer,
+    OidcSubject,
+    ImpersonatingUserId,
 }
 
 // endregion: --- User Types
@@ -75,22 +333,210 @@ impl DbBmc for UserBmc {
 }
 
 impl UserBmc {
+    /// Create the user with its hashed password, in one transaction
+    /// (the password is hashed with the `pwd_salt` generated by the insert).
+    pub async fn create(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        user_c: UserForCreate,
+    ) -> Result<i64> {
+        user_c.validate()?;
+        let UserForCreate {
+            username,
+            pwd_clear,
+        } = user_c;
+
+        let db = mm.db();
+        let mut conn = base::with_query_timeout(acquire(db)).await?;
+        let mut tx = base::with_query_timeout(conn.begin()).await?;
+
+        // -- Insert the user
+        let mut query = Query::insert();
+        query
+            .into_table(Self::table_ref())
+            .columns([UserIden::Username])
+            .values([username.clone().into()])?
+            .returning(
+                Query::returning().columns([UserIden::Id, UserIden::PwdSalt]),
+            );
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let res = base::with_query_timeout(
+            sqlx::query_as_with::<_, (i64, Uuid), _>(&sql, values)
+                .fetch_one(&mut *tx),
+        )
+        .await;
+        let (id, pwd_salt) = match res {
+            Err(Error::Sqlx(sqlx::Error::Database(ex)))
+                if ex.is_unique_violation() =>
+            {
+                return Err(Error::UserAlreadyExists { username });
+            }
+            res => res?,
+        };
+
+        // -- Set the password
+        let pwd = pwd::hash_pwd(&ContentToHash {
+            content: pwd_clear,
+            salt: pwd_salt,
+        })?;
+        let mut query = Query::update();
+        query
+            .table(Self::table_ref())
+            .value(UserIden::Pwd, SimpleExpr::from(pwd))
+            .values(base::audit_values(ctx))
+            .and_where(Expr::col(UserIden::Id).eq(id));
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        base::with_query_timeout(sqlx::query_with(&sql, values).execute(&mut *tx))
+            .await?;
+
+        base::with_query_timeout(tx.commit()).await?;
+        ctx.mark_write();
+
+        Ok(id)
+    }
+
+    /// Create the user of a first oidc login.
+    ///
+    /// Note: Never linked to an existing user (e.g., with the same username or
+    ///       email), which would let the provider users take over local ones.
+    pub async fn create_oidc(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        user_c: UserForOidcCreate,
+    ) -> Result<i64> {
+        user_c.validate()?;
+        let username = user_c.username.clone();
+        let oidc_subject = user_c.oidc_subject.clone();
+
+        base::create::<Self, _>(ctx, mm, user_c)
+            .await
+            .map_err(|ex| match ex {
+                Error::Sqlx(sqlx::Error::Database(ex))
+                    if ex.constraint() == Some("user_username_key") =>
+                {
+                    Error::UserAlreadyExists { username }
+                }
+                Error::Sqlx(sqlx::Error::Database(ex))
+                    if ex.constraint()
+                        == Some("user_oidc_issuer_oidc_subject_key") =>
+                {
+                    Error::OidcSubjectAlreadyExists { oidc_subject }
+                }
+                ex => ex,
+            })
+    }
+
     pub async fn get<E>(ctx: &Ctx, mm: &ModelManager, id: i64) -> Result<E>
     where
         E: UserBy,
     {
-        base::get::<Self, _>(ctx, mm, id).await
+        base::get::<Self, _>(&read_ctx::<E>(ctx), mm, id).await
+    }
+
+    pub async fn list(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        filters: Option<Vec<UserFilter>>,
+        list_options: Option<ListOptions>,
+    ) -> Result<Vec<User>> {
+        base::list::<Self, _, _>(ctx, mm, filters, list_options).await
+    }
+
+    pub async fn update(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        user_u: UserForUpdate,
+    ) -> Result<()> {
+        user_u.validate()?;
+        let username = user_u.username.clone();
+
+        base::update::<Self, _>(ctx, mm, id, user_u)
+            .await
+            .map_err(|ex| match (ex, username) {
+                (Error::Sqlx(sqlx::Error::Database(ex)), Some(username))
+                    if ex.is_unique_violation() =>
+                {
+                    Error::UserAlreadyExists { username }
+                }
+                (ex, _) => ex,
+            })
+    }
+
+    /// Update the profile, by the user itself (see `update` for the admins).
+    pub async fn update_profile(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        profile_u: UserForProfileUpdate,
+    ) -> Result<()> {
+        profile_u.validate()?;
+
+        base::update::<Self, _>(ctx, mm, id, profile_u).await
+    }
+
+    /// Disable (or enable back) the user login and sessions
+    /// (checked at login, and on each request).
+    pub async fn set_disabled(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        disabled: bool,
+    ) -> Result<()> {
+        base::update::<Self, _>(ctx, mm, id, UserForDisable { disabled }).await
+    }
+
+    /// Start (with `Some(user_id)`) or stop (with `None`) the impersonation by
+    /// the admin `id`, which its impersonation tokens are checked against.
+    ///
+    /// Note: One impersonation at a time per admin, so starting another one
+    ///       ends the previous one.
+    pub async fn set_impersonating(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        user_id: Option<i64>,
+    ) -> Result<()> {
+        let db = mm.db();
+
+        // -- Build query
+        // Note: Not with `base::update`, which skips the `None` values.
+        let mut query = Query::update();
+        query
+            .table(Self::table_ref())
+            .value(UserIden::ImpersonatingUserId, user_id)
+            .values(base::audit_values(ctx))
+            .and_where(Expr::col(UserIden::Id).eq(id));
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let count = base::with_query_timeout(async {
+            let mut conn = acquire(db).await?;
+            sqlx::query_with(&sql, values).execute(&mut *conn).await
+        })
+        .await?
+        .rows_affected();
+        ctx.mark_write();
+
+        if count == 0 {
+            Err(Error::EntityNotFound {
+                entity: Self::TABLE,
+                id,
+            })
+        } else {
+            Ok(())
+        }
     }
 
     pub async fn first_by_username<E>(
-        _ctx: &Ctx,
+        ctx: &Ctx,
         mm: &ModelManager,
         username: &str,
     ) -> Result<Option<E>>
     where
         E: UserBy,
     {
-        let db = mm.db();
+        let db = mm.db_read(&read_ctx::<E>(ctx));
 
         // -- Build query
         let mut query = Query::select();
@@ -101,23 +547,147 @@ impl UserBmc {
 
         // -- Exec query
         let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-        let user = sqlx::query_as_with::<_, E, _>(&sql, values)
-            .fetch_optional(db)
-            .await?;
+        let user = base::with_query_timeout(async {
+            let mut conn = acquire(db).await?;
+            sqlx::query_as_with::<_, E, _>(&sql, values)
+                .fetch_optional(&mut *conn)
+                .await
+        })
+        .await?;
+
+        Ok(user)
+    }
+
+    pub async fn first_by_oidc_subject<E>(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        oidc_issuer: &str,
+        oidc_subject: &str,
+    ) -> Result<Option<E>>
+    where
+        E: UserBy,
+    {
+        let db = mm.db_read(&read_ctx::<E>(ctx));
+
+        // -- Build query
+        let mut query = Query::select();
+        query
+            .from(Self::table_ref())
+            .columns(E::field_idens())
+            .and_where(Expr::col(UserIden::OidcIssuer).eq(oidc_issuer))
+            .and_where(Expr::col(UserIden::OidcSubject).eq(oidc_subject));
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let user = base::with_query_timeout(async {
+            let mut conn = acquire(db).await?;
+            sqlx::query_as_with::<_, E, _>(&sql, values)
+                .fetch_optional(&mut *conn)
+                .await
+        })
+        .await?;
 
         Ok(user)
     }
 
+    /// Set the password, and rotate the `token_salt`
+    /// (i.e., invalidate the web tokens and pwd reset tokens of the user).
     pub async fn update_pwd(
         ctx: &Ctx,
         mm: &ModelManager,
         id: i64,
         pwd_clear: &str,
+    ) -> Result<()> {
+        Self::set_pwd(ctx, mm, id, pwd_clear, None).await
+    }
+
+    /// Change the password, after checking the current one.
+    ///
+    /// Note: Since the `token_salt` is rotated, all of the user sessions
+    ///       (including the current one) have to log in again.
+    pub async fn change_pwd(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        pwd_current: &str,
+        pwd_clear: &str,
+    ) -> Result<()> {
+        Self::check_pwd(ctx, mm, id, pwd_current).await?;
+
+        Self::set_pwd(ctx, mm, id, pwd_clear, None).await
+    }
+
+    /// Change the email, after checking the current password.
+    ///
+    /// Note: Not part of the profile update, the email being where the pwd
+    ///       reset mails go (i.e., a session alone, such as a stolen one, must
+    ///       not be enough to redirect them and take over the account).
+    pub async fn change_email(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        pwd_current: &str,
+        email: &str,
+    ) -> Result<()> {
+        let email_u = UserForEmailUpdate {
+            email: email.to_string(),
+        };
+        email_u.validate()?;
+        Self::check_pwd(ctx, mm, id, pwd_current).await?;
+
+        base::update::<Self, _>(ctx, mm, id, email_u).await
+    }
+
+    async fn check_pwd(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        pwd_current: &str,
+    ) -> Result<()> {
+        let user: UserForLogin = Self::get(ctx, mm, id).await?;
+        let to_hash = ContentToHash {
+            content: pwd_current.to_string(),
+            salt: user.pwd_salt,
+        };
+        let is_matching = user
+            .pwd
+            .is_some_and(|pwd| pwd::validate_pwd(&to_hash, &pwd).is_ok());
+        if !is_matching {
+            return Err(Error::PwdNotMatching { user_id: id });
+        }
+
+        Ok(())
+    }
+
+    /// Reset the password (with a pwd reset token already validated against
+    /// `token_salt`).
+    ///
+    /// Note: Only when the `token_salt` was not rotated since (e.g., by another
+    ///       reset with the same token), so that the token is single use.
+    pub async fn reset_pwd(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        token_salt: Uuid,
+        pwd_clear: &str,
+    ) -> Result<()> {
+        Self::set_pwd(ctx, mm, id, pwd_clear, Some(token_salt)).await
+    }
+
+    /// Note: Where all of the password updates go through, so that they all
+    ///       meet the pwd policy.
+    async fn set_pwd(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        pwd_clear: &str,
+        token_salt: Option<Uuid>,
     ) -> Result<()> {
         let db = mm.db();
 
         // -- Prep password
         let user: UserForLogin = Self::get(ctx, mm, id).await?;
+        validate_pwd_clear(pwd_clear, &user.username)?;
         let pwd = pwd::hash_pwd(&ContentToHash {
             content: pwd_clear.to_string(),
             salt: user.pwd_salt,
@@ -128,25 +698,223 @@ impl UserBmc {
         query
             .table(Self::table_ref())
             .value(UserIden::Pwd, SimpleExpr::from(pwd))
+            .value(UserIden::TokenSalt, Expr::cust("gen_random_uuid()"))
+            .values(base::audit_values(ctx))
             .and_where(Expr::col(UserIden::Id).eq(id));
+        if let Some(token_salt) = token_salt {
+            query.and_where(Expr::col(UserIden::TokenSalt).eq(token_salt));
+        }
 
         // -- Exec query
         let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
-        let _count = sqlx::query_with(&sql, values)
-            .execute(db)
-            .await?
-            .rows_affected();
+        let count = base::with_query_timeout(async {
+            let mut conn = acquire(db).await?;
+            sqlx::query_with(&sql, values).execute(&mut *conn).await
+        })
+        .await?
+        .rows_affected();
+        ctx.mark_write();
 
-        Ok(())
+        if count == 0 {
+            Err(Error::TokenSaltRotated { user_id: id })
+        } else {
+            Ok(())
+        }
     }
 }
 
+/// Totp second factor.
+impl UserBmc {
+    /// Start (or restart) the totp enrollment with a new secret, which is only
+    /// enabled once confirmed with a code (see `totp_confirm`).
+    pub async fn totp_enroll(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+    ) -> Result<TotpEnrollment> {
+        let user: UserForTotp = Self::get(ctx, mm, id).await?;
+        if user.totp_enabled {
+            return Err(Error::TotpAlreadyEnabled { user_id: id });
+        }
+
+        let secret = totp::generate_secret();
+        let totp_u = UserForTotpUpdate {
+            totp_secret_enc: Some(totp::encrypt_secret(&secret)?),
+            totp_last_step: Some(0),
+            ..Default::default()
+        };
+        base::update::<Self, _>(ctx, mm, id, totp_u).await?;
+
+        Ok(TotpEnrollment {
+            secret: totp::secret_b32(&secret),
+            otpauth_uri: totp::otpauth_uri(&user.username, &secret),
+        })
+    }
+
+    /// Confirm the enrollment with a first code, and enable the second factor.
+    ///
+    /// Returns the clear recovery codes (only stored hashed, so only given here).
+    pub async fn totp_confirm(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        code: &str,
+    ) -> Result<Vec<String>> {
+        let user: UserForTotp = Self::get(ctx, mm, id).await?;
+        if user.totp_enabled {
+            return Err(Error::TotpAlreadyEnabled { user_id: id });
+        }
+        let Some(secret_enc) = user.totp_secret_enc else {
+            return Err(Error::TotpNotEnrolled { user_id: id });
+        };
+
+        // -- Validate the code.
+        let secret = totp::decrypt_secret(&secret_enc)?;
+        let step = validate_totp_code(id, &secret, code, user.totp_last_step)?;
+
+        // -- Enable, with new recovery codes.
+        let recovery_codes = totp::generate_recovery_codes();
+        let recovery_hashes = recovery_codes
+            .iter()
+            .map(|code| hash_recovery_code(code, user.pwd_salt))
+            .collect::<Result<Vec<_>>>()?;
+
+        let totp_u = UserForTotpUpdate {
+            totp_enabled: Some(true),
+            totp_last_step: Some(step),
+            totp_recovery_codes: Some(recovery_hashes.join(" ")),
+            ..Default::default()
+        };
+        base::update::<Self, _>(ctx, mm, id, totp_u).await?;
+
+        Ok(recovery_codes)
+    }
+
+    /// Verify the second factor with a totp code, or a recovery code
+    /// (which is then consumed).
+    pub async fn totp_verify(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        code: &str,
+    ) -> Result<()> {
+        let user: UserForTotp = Self::get(ctx, mm, id).await?;
+        let (true, Some(secret_enc)) = (user.totp_enabled, user.totp_secret_enc)
+        else {
+            return Err(Error::TotpNotEnabled { user_id: id });
+        };
+
+        // -- Totp code.
+        let secret = totp::decrypt_secret(&secret_enc)?;
+        if let Ok(step) = validate_totp_code(id, &secret, code, user.totp_last_step)
+        {
+            return Self::totp_swap(
+                ctx,
+                mm,
+                id,
+                UserIden::TotpLastStep,
+                user.totp_last_step.into(),
+                step.into(),
+            )
+            .await;
+        }
+
+        // -- Recovery code.
+        let recovery_codes = user.totp_recovery_codes.unwrap_or_default();
+        let mut recovery_hashes: Vec<&str> =
+            recovery_codes.split_whitespace().collect();
+        let to_hash = ContentToHash {
+            content: totp::normalize_recovery_code(code),
+            salt: user.pwd_salt,
+        };
+        let Some(idx) = recovery_hashes
+            .iter()
+            .position(|hash| pwd::validate_pwd(&to_hash, hash).is_ok())
+        else {
+            return Err(Error::TotpCodeNotMatching { user_id: id });
+        };
+
+        recovery_hashes.remove(idx);
+        let remaining_codes = recovery_hashes.join(" ");
+        Self::totp_swap(
+            ctx,
+            mm,
+            id,
+            UserIden::TotpRecoveryCodes,
+            recovery_codes.into(),
+            remaining_codes.into(),
+        )
+        .await
+    }
+
+    /// Set the totp column only if it still has the `expected` value, so that
+    /// a code cannot be used twice by concurrent logins.
+    async fn totp_swap(
+        ctx: &Ctx,
+        mm: &ModelManager,
+        id: i64,
+        column: UserIden,
+        expected: SimpleExpr,
+        value: SimpleExpr,
+    ) -> Result<()> {
+        let db = mm.db();
+
+        // -- Build query
+        let mut query = Query::update();
+        query
+            .table(Self::table_ref())
+            .value(column, value)
+            .values(base::audit_values(ctx))
+            .and_where(Expr::col(UserIden::Id).eq(id))
+            .and_where(Expr::col(column).eq(expected));
+
+        // -- Exec query
+        let (sql, values) = query.build_sqlx(PostgresQueryBuilder);
+        let count = base::with_query_timeout(async {
+            let mut conn = acquire(db).await?;
+            sqlx::query_with(&sql, values).execute(&mut *conn).await
+        })
+        .await?
+        .rows_affected();
+        ctx.mark_write();
+
+        if count == 0 {
+            Err(Error::TotpCodeNotMatching { user_id: id })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+fn validate_totp_code(
+    user_id: i64,
+    secret: &[u8],
+    code: &str,
+    last_step: i64,
+) -> Result<i64> {
+    totp::validate_code(secret, code, last_step).map_err(|ex| match ex {
+        totp::Error::CodeNotMatching => Error::TotpCodeNotMatching { user_id },
+        ex => Error::Totp(ex),
+    })
+}
+
+/// Recovery codes are hashed like the passwords (with the user pwd salt).
+fn hash_recovery_code(code: &str, salt: Uuid) -> Result<String> {
+    let hash = pwd::hash_pwd(&ContentToHash {
+        content: totp::normalize_recovery_code(code),
+        salt,
+    })?;
+
+    Ok(hash)
+}
+
 // region:    --- Tests
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::_dev_utils;
     use anyhow::{Context, Result};
+    use serde_json::json;
     use serial_test::serial;
 
     #[serial]
@@ -167,5 +935,394 @@ mod tests {
 
         Ok(())
     }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_ok_and_err_already_exists() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_username = "test_create_ok-user";
+        let fx_pwd_clear = "Test_create_ok pwd";
+        let new_user_c = || UserForCreate {
+            username: fx_username.to_string(),
+            pwd_clear: fx_pwd_clear.to_string(),
+        };
+
+        // -- Exec
+        let id = UserBmc::create(&ctx, &mm, new_user_c()).await?;
+        let res = UserBmc::create(&ctx, &mm, new_user_c()).await;
+
+        // -- Check
+        let user: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(user.username, fx_username);
+        pwd::validate_pwd(
+            &ContentToHash {
+                content: fx_pwd_clear.to_string(),
+                salt: user.pwd_salt,
+            },
+            &user.pwd.context("Should have a pwd")?,
+        )?;
+        assert!(
+            matches!(
+                &res,
+                Err(Error::UserAlreadyExists { username }) if username == fx_username
+            ),
+            "Should have matched `Err(Error::UserAlreadyExists)` but was `{res:?}`"
+        );
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_change_pwd_ok_and_err_not_matching() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_pwd_clear = "Test_change_pwd_ok pwd";
+        let fx_pwd_new = "Test_change_pwd_ok pwd new";
+        let id = UserBmc::create(
+            &ctx,
+            &mm,
+            UserForCreate {
+                username: "test_change_pwd_ok-user".to_string(),
+                pwd_clear: fx_pwd_clear.to_string(),
+            },
+        )
+        .await?;
+        let user_before: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+
+        // -- Exec
+        let res_wrong =
+            UserBmc::change_pwd(&ctx, &mm, id, "Wrong pwd", fx_pwd_new).await;
+        let user_wrong: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+        UserBmc::change_pwd(&ctx, &mm, id, fx_pwd_clear, fx_pwd_new).await?;
+
+        // -- Check
+        assert!(
+            matches!(
+                &res_wrong,
+                Err(Error::PwdNotMatching { user_id }) if *user_id == id
+            ),
+            "Should have matched `Err(Error::PwdNotMatching)` but was `{res_wrong:?}`"
+        );
+        assert_eq!(user_wrong.pwd, user_before.pwd, "pwd should be unchanged");
+        assert_eq!(user_wrong.token_salt, user_before.token_salt);
+        let user: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+        pwd::validate_pwd(
+            &ContentToHash {
+                content: fx_pwd_new.to_string(),
+                salt: user.pwd_salt,
+            },
+            &user.pwd.context("Should have a pwd")?,
+        )?;
+        assert_ne!(user.token_salt, user_before.token_salt);
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_reset_pwd_ok_and_err_single_use() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let id = UserBmc::create(
+            &ctx,
+            &mm,
+            UserForCreate {
+                username: "test_reset_pwd_ok-user".to_string(),
+                pwd_clear: "Test_reset_pwd_ok pwd".to_string(),
+            },
+        )
+        .await?;
+        let fx_token_salt = UserBmc::get::<UserForLogin>(&ctx, &mm, id)
+            .await?
+            .token_salt;
+
+        // -- Exec
+        UserBmc::reset_pwd(&ctx, &mm, id, fx_token_salt, "Test_reset_pwd_ok new1")
+            .await?;
+        let res_again = UserBmc::reset_pwd(
+            &ctx,
+            &mm,
+            id,
+            fx_token_salt,
+            "Test_reset_pwd_ok new2",
+        )
+        .await;
+
+        // -- Check
+        assert!(
+            matches!(
+                &res_again,
+                Err(Error::TokenSaltRotated { user_id }) if *user_id == id
+            ),
+            "Should have matched `Err(Error::TokenSaltRotated)` but was `{res_again:?}`"
+        );
+        let user: UserForLogin = UserBmc::get(&ctx, &mm, id).await?;
+        pwd::validate_pwd(
+            &ContentToHash {
+                content: "Test_reset_pwd_ok new1".to_string(),
+                salt: user.pwd_salt,
+            },
+            &user.pwd.context("Should have a pwd")?,
+        )?;
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_change_email_ok_and_err_not_matching() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_pwd_clear = "Test_change_email_ok pwd";
+        let fx_email = "test_change_email_ok@localhost";
+        let id = UserBmc::create(
+            &ctx,
+            &mm,
+            UserForCreate {
+                username: "test_change_email_ok-user".to_string(),
+                pwd_clear: fx_pwd_clear.to_string(),
+            },
+        )
+        .await?;
+
+        // -- Exec
+        let res_wrong =
+            UserBmc::change_email(&ctx, &mm, id, "Wrong pwd", "other@localhost")
+                .await;
+        let res_invalid =
+            UserBmc::change_email(&ctx, &mm, id, fx_pwd_clear, "not-an-email").await;
+        UserBmc::change_email(&ctx, &mm, id, fx_pwd_clear, fx_email).await?;
+
+        // -- Check
+        assert!(
+            matches!(&res_wrong, Err(Error::PwdNotMatching { .. })),
+            "Should have matched `Err(Error::PwdNotMatching)` but was `{res_wrong:?}`"
+        );
+        assert!(
+            matches!(&res_invalid, Err(Error::Validate(_))),
+            "Should have matched `Err(Error::Validate)` but was `{res_invalid:?}`"
+        );
+        let profile: UserProfile = UserBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(profile.email.as_deref(), Some(fx_email));
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_oidc_ok_first_by_subject() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_issuer = "https://issuer.example.com";
+        let fx_subject = "test_create_oidc_ok-subject";
+        let fx_user_c = UserForOidcCreate {
+            username: "test_create_oidc_ok-user".to_string(),
+            email: None,
+            oidc_issuer: fx_issuer.to_string(),
+            oidc_subject: fx_subject.to_string(),
+        };
+
+        // -- Exec
+        let id = UserBmc::create_oidc(&ctx, &mm, fx_user_c).await?;
+        let other_issuer_user: Option<User> =
+            UserBmc::first_by_oidc_subject(&ctx, &mm, "https://other", fx_subject)
+                .await?;
+
+        // -- Check
+        let user: UserForLogin =
+            UserBmc::first_by_oidc_subject(&ctx, &mm, fx_issuer, fx_subject)
+                .await?
+                .context("Should have the oidc user")?;
+        assert_eq!(user.id, id);
+        assert!(user.pwd.is_none(), "Should not have a pwd");
+        assert!(other_issuer_user.is_none(), "Should not match other issuer");
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_create_oidc_err_subject_exists() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_subject = "test_create_oidc_err-subject";
+        let fx_user_c = |username: &str| UserForOidcCreate {
+            username: username.to_string(),
+            email: None,
+            oidc_issuer: "https://issuer.example.com".to_string(),
+            oidc_subject: fx_subject.to_string(),
+        };
+        let id =
+            UserBmc::create_oidc(&ctx, &mm, fx_user_c("test_create_oidc_err-1"))
+                .await?;
+
+        // -- Exec
+        let res =
+            UserBmc::create_oidc(&ctx, &mm, fx_user_c("test_create_oidc_err-2"))
+                .await;
+
+        // -- Check
+        assert!(
+            matches!(
+                &res,
+                Err(Error::OidcSubjectAlreadyExists { oidc_subject })
+                    if oidc_subject == fx_subject
+            ),
+            "Should have matched `Err(Error::OidcSubjectAlreadyExists)` but was `{res:?}`"
+        );
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_and_set_disabled_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_username_new = "test_update_ok-user-new";
+        let fx_email = "test_update_ok@localhost";
+        let fx_user_c = UserForCreate {
+            username: "test_update_ok-user".to_string(),
+            pwd_clear: "Test_update_ok pwd".to_string(),
+        };
+        let id = UserBmc::create(&ctx, &mm, fx_user_c).await?;
+
+        // -- Exec
+        let user_u = UserForUpdate {
+            username: Some(fx_username_new.to_string()),
+            email: Some(fx_email.to_string()),
+        };
+        UserBmc::update(&ctx, &mm, id, user_u).await?;
+        UserBmc::set_disabled(&ctx, &mm, id, true).await?;
+
+        // -- Check
+        let filters: Vec<UserFilter> = serde_json::from_value(json!([{
+            "username": {"$startsWith": "test_update_ok"},
+            "disabled": true
+        }]))?;
+        let users = UserBmc::list(&ctx, &mm, Some(filters), None).await?;
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].id, id);
+        assert_eq!(users[0].username, fx_username_new);
+        assert_eq!(users[0].email.as_deref(), Some(fx_email));
+        assert!(users[0].disabled);
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_set_impersonating_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_user_c = UserForCreate {
+            username: "test_set_impersonating_ok-user".to_string(),
+            pwd_clear: "Test_set_impersonating_ok pwd".to_string(),
+        };
+        let fx_user_id = UserBmc::create(&ctx, &mm, fx_user_c).await?;
+        let fx_admin: User = UserBmc::first_by_username(&ctx, &mm, "demo1")
+            .await?
+            .context("Should have user 'demo1'")?;
+
+        // -- Exec
+        UserBmc::set_impersonating(&ctx, &mm, fx_admin.id, Some(fx_user_id)).await?;
+        let admin: UserForAuth = UserBmc::get(&ctx, &mm, fx_admin.id).await?;
+        let impersonating_user_id = admin.impersonating_user_id;
+        UserBmc::set_impersonating(&ctx, &mm, fx_admin.id, None).await?;
+
+        // -- Check
+        assert_eq!(impersonating_user_id, Some(fx_user_id));
+        let admin: UserForAuth = UserBmc::get(&ctx, &mm, fx_admin.id).await?;
+        assert!(
+            admin.impersonating_user_id.is_none(),
+            "Should have stopped the impersonation"
+        );
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, fx_user_id).await?;
+
+        Ok(())
+    }
+
+    #[serial]
+    #[tokio::test]
+    async fn test_update_profile_ok_and_err_validate() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let ctx = Ctx::root_ctx();
+        let fx_user_c = UserForCreate {
+            username: "test_update_profile_ok-user".to_string(),
+            pwd_clear: "Test_update_profile_ok pwd".to_string(),
+        };
+        let id = UserBmc::create(&ctx, &mm, fx_user_c).await?;
+
+        // -- Exec
+        let profile_u = UserForProfileUpdate {
+            display_name: Some("Test Profile".to_string()),
+            timezone: Some("America/Argentina/Buenos_Aires".to_string()),
+            locale: Some("es-AR".to_string()),
+        };
+        UserBmc::update_profile(&ctx, &mm, id, profile_u).await?;
+        let invalid_profile_u = UserForProfileUpdate {
+            timezone: Some("Europe/../Paris".to_string()),
+            locale: Some("es_AR".to_string()),
+            ..Default::default()
+        };
+        let res = UserBmc::update_profile(&ctx, &mm, id, invalid_profile_u).await;
+
+        // -- Check
+        let profile: UserProfile = UserBmc::get(&ctx, &mm, id).await?;
+        assert_eq!(profile.display_name.as_deref(), Some("Test Profile"));
+        assert_eq!(
+            profile.timezone.as_deref(),
+            Some("America/Argentina/Buenos_Aires")
+        );
+        assert_eq!(profile.locale.as_deref(), Some("es-AR"));
+        assert!(profile.email.is_none(), "Should not have an email");
+        assert!(
+            matches!(
+                &res,
+                Err(Error::Validate(validate::Error::FieldsInvalid(field_errors)))
+                    if field_errors.len() == 2
+            ),
+            "Should have been `timezone` and `locale` validation errors but was `{res:?}`"
+        );
+
+        // -- Clean
+        base::delete::<UserBmc>(&ctx, &mm, id).await?;
+
+        Ok(())
+    }
 }
 // endregion: --- Tests
@@ -0,0 +1,108 @@
+use derive_more::From;
+use lib_utils::validate::FieldError;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[serde_as]
+#[derive(Debug, Serialize, From)]
+pub enum Error {
+    BaseUrlInvalid(String),
+
+    // -- Auth
+    /// The password was accepted, but the login must be completed with
+    /// `login_totp` (and this challenge).
+    LoginSecondFactorRequired {
+        challenge: String,
+    },
+
+    // -- Rpc
+    RpcParamsInvalid {
+        rpc_method: String,
+        param_path: String,
+        cause: String,
+    },
+
+    // -- Response
+    /// Error envelope returned by the service.
+    Api {
+        req_uuid: Option<String>,
+        error: ApiError,
+    },
+    ResponseUnexpected {
+        status: u16,
+        body: String,
+    },
+
+    // -- External Modules
+    #[from]
+    Reqwest(#[serde_as(as = "DisplayFromStr")] reqwest::Error),
+    #[from]
+    SerdeJson(#[serde_as(as = "DisplayFromStr")] serde_json::Error),
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(
+        &self,
+        fmt: &mut core::fmt::Formatter,
+    ) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
+
+// region:    --- Api Error
+
+/// The `ClientError` of the web-server, as found in the error envelope
+/// (`error.message` and `error.data.detail`).
+///
+/// Note: Must be kept in sync with `web::ClientError`. Unknown messages
+///       (e.g., from a newer server) are decoded as `UNKNOWN`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "message", content = "detail")]
+#[allow(non_camel_case_types)]
+pub enum ApiError {
+    LOGIN_FAIL,
+    LOGIN_LOCKED { retry_after_sec: u64 },
+    USER_DISABLED,
+    REGISTRATION_DISABLED,
+    OIDC_DISABLED,
+    USERNAME_ALREADY_EXISTS { username: String },
+    PWD_RESET_TOKEN_INVALID,
+    PWD_NOT_MATCHING,
+    NO_AUTH,
+    ADMIN_REQUIRED,
+    IMPERSONATION_BLOCKED,
+    IMPERSONATION_TARGET_INVALID { user_id: i64 },
+    CSRF_CHECK_FAIL,
+    RATE_LIMITED { retry_after_sec: u64 },
+    ENTITY_NOT_FOUND { entity: String, id: i64 },
+    LIST_LIMIT_OVER_MAX { max: i64, actual: i64 },
+    TOTP_CODE_INVALID,
+    TOTP_ALREADY_ENABLED,
+    TOTP_NOT_ENROLLED,
+
+    RPC_REQUEST_INVALID { cause: String },
+    RPC_METHOD_UNKNOWN { rpc_method: String },
+    RPC_MISSING_PARAMS { rpc_method: String },
+    RPC_INVALID_PARAMS {
+        rpc_method: String,
+        param_path: String,
+        cause: String,
+    },
+    VALIDATION_FAILED { field_errors: Vec<FieldError> },
+
+    SSE_INVALID_FILTERS { cause: String },
+
+    SERVICE_TIMEOUT,
+    SERVICE_ERROR,
+
+    #[serde(other)]
+    UNKNOWN,
+}
+
+// endregion: --- Api Error
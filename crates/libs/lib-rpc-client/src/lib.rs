@@ -0,0 +1,341 @@
+//! Typed client for the web-server `/api/rpc` JSON-RPC API.
+//!
+//! - Reuses the lib-core and lib-rpc types (e.g., `TaskForCreate`, `Task`,
+//!   `ParamsList<TaskFilter>`), so requests and responses are checked at
+//!   compile time rather than hand-written with `json!`.
+//! - Auth is either the `auth-token` (or `__Host-auth-token`) cookie, set by
+//!   `login` and kept in the client cookie jar, or a bearer token given with
+//!   `with_bearer_token`.
+//! - Error envelopes are decoded into `Error::Api` with the typed `ApiError`.
+//!
+//! ```ignore
+//! let client = RpcClient::new("http://localhost:8080")?;
+//! client.login("demo1", "Welcome-demo1").await?;
+//! let task = client.create_task(TaskForCreate { title: "title1".into() }).await?;
+//! ```
+//!
+
+// region:    --- Modules
+
+mod error;
+mod list_params;
+mod task_rpc;
+mod totp_rpc;
+mod user_rpc;
+
+pub use self::error::{ApiError, Error, Result};
+pub use self::list_params::{FilterGroup, ListParams};
+
+use reqwest::cookie::{CookieStore, Jar};
+use reqwest::Url;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// endregion: --- Modules
+
+/// Note: Same as the web-server `web::AUTH_TOKEN`
+///       (with the optional `__Host-` prefix of its cookie policy).
+const AUTH_TOKEN: &str = "auth-token";
+const AUTH_TOKEN_HOST_PREFIX: &str = "__Host-";
+
+pub struct RpcClient {
+    base_url: Url,
+    http: reqwest::Client,
+    cookie_jar: Arc<Jar>,
+    bearer_token: Option<String>,
+    next_rpc_id: AtomicU64,
+}
+
+// Constructors.
+impl RpcClient {
+    /// New client for the service at `base_url` (e.g., `http://localhost:8080`),
+    /// authenticated by cookie once `login` succeeds.
+    pub fn new(base_url: &str) -> Result<Self> {
+        let base_url =
+            Url::parse(base_url).map_err(|ex| Error::BaseUrlInvalid(ex.to_string()))?;
+        let cookie_jar = Arc::new(Jar::default());
+        let http = reqwest::Client::builder()
+            .cookie_provider(cookie_jar.clone())
+            .build()?;
+
+        Ok(RpcClient {
+            base_url,
+            http,
+            cookie_jar,
+            bearer_token: None,
+            next_rpc_id: AtomicU64::new(1),
+        })
+    }
+
+    /// Authenticate with an `Authorization: Bearer` token rather than the cookie
+    /// (e.g., a token obtained by another client with `auth_token`).
+    pub fn with_bearer_token(mut self, token: impl Into<String>) -> Self {
+        self.bearer_token = Some(token.into());
+        self
+    }
+}
+
+// Auth.
+impl RpcClient {
+    /// Self-service registration (when enabled on the service), returns the
+    /// new user id (the user still has to `login`).
+    pub async fn register(&self, username: &str, pwd: &str) -> Result<i64> {
+        let body = json!({
+            "username": username,
+            "pwd_clear": pwd
+        });
+        let res = self.post::<Value>("/api/register", &body).await?;
+
+        Ok(serde_json::from_value(res["user_id"].clone())?)
+    }
+
+    pub async fn login(&self, username: &str, pwd: &str) -> Result<()> {
+        let body = json!({
+            "username": username,
+            "pwd": pwd
+        });
+        let res = self.post::<Value>("/api/login", &body).await?;
+
+        if res["second_factor_required"].as_bool() == Some(true) {
+            let challenge = res["challenge"].as_str().unwrap_or_default();
+            return Err(Error::LoginSecondFactorRequired {
+                challenge: challenge.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Complete a login with its second factor
+    /// (after `Error::LoginSecondFactorRequired`).
+    pub async fn login_totp(&self, challenge: &str, code: &str) -> Result<()> {
+        let body = json!({
+            "challenge": challenge,
+            "code": code
+        });
+        self.post::<Value>("/api/login/totp", &body).await?;
+
+        Ok(())
+    }
+
+    /// Request a pwd reset mail (always succeeds, whether or not the user exists).
+    pub async fn request_pwd_reset(&self, username: &str) -> Result<()> {
+        let body = json!({ "username": username });
+        self.post::<Value>("/api/pwd_reset/request", &body).await?;
+
+        Ok(())
+    }
+
+    /// Reset the password with the token of the pwd reset mail.
+    pub async fn reset_pwd(&self, token: &str, pwd: &str) -> Result<()> {
+        let body = json!({
+            "token": token,
+            "pwd_clear": pwd
+        });
+        self.post::<Value>("/api/pwd_reset", &body).await?;
+
+        Ok(())
+    }
+
+    pub async fn logoff(&self) -> Result<()> {
+        self.post::<Value>("/api/logoff", &json!({ "logoff": true }))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Returns the current auth token (bearer token, or the token cookie
+    /// set by the last response).
+    pub fn auth_token(&self) -> Option<String> {
+        if let Some(token) = &self.bearer_token {
+            return Some(token.clone());
+        }
+
+        let cookies = self.cookie_jar.cookies(&self.base_url)?;
+        cookies
+            .to_str()
+            .ok()?
+            .split("; ")
+            .map(|cookie| {
+                cookie.strip_prefix(AUTH_TOKEN_HOST_PREFIX).unwrap_or(cookie)
+            })
+            .find_map(|cookie| cookie.strip_prefix(&format!("{AUTH_TOKEN}=")))
+            .map(|token| token.to_string())
+    }
+}
+
+// Rpc.
+impl RpcClient {
+    /// Call any rpc method, with its typed params and result.
+    ///
+    /// Note: The typed methods (e.g., `create_task`) should be preferred.
+    pub async fn call<P, R>(&self, rpc_method: &str, params: Option<P>) -> Result<R>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let rpc_id = self.next_rpc_id.fetch_add(1, Ordering::Relaxed);
+        let body = json!({
+            "id": rpc_id,
+            "method": rpc_method,
+            "params": params
+        });
+
+        self.post("/api/rpc", &body).await
+    }
+
+    async fn post<R: DeserializeOwned>(&self, path: &str, body: &Value) -> Result<R> {
+        let url = self
+            .base_url
+            .join(path)
+            .map_err(|ex| Error::BaseUrlInvalid(ex.to_string()))?;
+
+        let mut req = self.http.post(url).json(body);
+        if let Some(token) = &self.bearer_token {
+            req = req.bearer_auth(token);
+        }
+
+        let res = req.send().await?;
+        let status = res.status().as_u16();
+        let body = res.text().await?;
+
+        decode_response(status, &body)
+    }
+}
+
+// region:    --- Response Decoding
+
+/// Decode the json-rpc response body into its `result`, or into `Error::Api`
+/// when it is an error envelope.
+fn decode_response<R: DeserializeOwned>(status: u16, body: &str) -> Result<R> {
+    let unexpected = || Error::ResponseUnexpected {
+        status,
+        body: body.to_string(),
+    };
+
+    let mut res_json: Value = serde_json::from_str(body).map_err(|_| unexpected())?;
+
+    if let Some(error) = res_json.get("error") {
+        return Err(decode_error_envelope(error));
+    }
+
+    match res_json.get_mut("result") {
+        Some(result) if (200..300).contains(&status) => {
+            Ok(serde_json::from_value(result.take())?)
+        }
+        _ => Err(unexpected()),
+    }
+}
+
+/// Decode the `error` of the envelope built by the web-server
+/// `client_error_body` (i.e., `message`, `data.req_uuid`, `data.detail`).
+fn decode_error_envelope(error: &Value) -> Error {
+    let req_uuid = error
+        .pointer("/data/req_uuid")
+        .and_then(Value::as_str)
+        .map(|req_uuid| req_uuid.to_string());
+
+    let mut api_error = json!({ "message": error.get("message") });
+    if let Some(detail) = error.pointer("/data/detail").filter(|d| !d.is_null()) {
+        api_error["detail"] = detail.clone();
+    }
+    let error = serde_json::from_value(api_error).unwrap_or(ApiError::UNKNOWN);
+
+    Error::Api { req_uuid, error }
+}
+
+// endregion: --- Response Decoding
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use lib_core::model::task::Task;
+
+    #[test]
+    fn test_decode_response_result_ok() -> Result<()> {
+        // -- Fixtures
+        let fx_body = r#"{"id": 1, "result": {"id": 1000, "title": "t1", "done": false}}"#;
+
+        // -- Exec
+        let task: Task = decode_response(200, fx_body)?;
+
+        // -- Check
+        assert_eq!(task.id, 1000);
+        assert_eq!(task.title, "t1");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_response_err_entity_not_found() -> Result<()> {
+        // -- Fixtures
+        let fx_body = r#"{
+            "id": 1,
+            "error": {
+                "message": "ENTITY_NOT_FOUND",
+                "data": {
+                    "req_uuid": "fx-uuid",
+                    "detail": {"entity": "task", "id": 100}
+                }
+            }
+        }"#;
+
+        // -- Exec
+        let res = decode_response::<Task>(400, fx_body);
+
+        // -- Check
+        assert!(
+            matches!(
+                &res,
+                Err(Error::Api {
+                    req_uuid: Some(req_uuid),
+                    error: ApiError::ENTITY_NOT_FOUND { entity, id: 100 },
+                }) if req_uuid == "fx-uuid" && entity == "task"
+            ),
+            "Should have been `ENTITY_NOT_FOUND` but was `{res:?}`"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_response_err_unit_and_unknown() -> Result<()> {
+        // -- Fixtures
+        let fx_no_auth = r#"{"id": null, "error": {"message": "NO_AUTH", "data": {"req_uuid": "fx-uuid", "detail": null}}}"#;
+        let fx_unknown = r#"{"id": null, "error": {"message": "SOME_NEW_ERROR", "data": {"req_uuid": "fx-uuid"}}}"#;
+
+        // -- Exec
+        let res_no_auth = decode_response::<Value>(403, fx_no_auth);
+        let res_unknown = decode_response::<Value>(400, fx_unknown);
+
+        // -- Check
+        assert!(
+            matches!(
+                res_no_auth,
+                Err(Error::Api {
+                    error: ApiError::NO_AUTH,
+                    ..
+                })
+            ),
+            "Should have been `NO_AUTH` but was `{res_no_auth:?}`"
+        );
+        assert!(
+            matches!(
+                res_unknown,
+                Err(Error::Api {
+                    error: ApiError::UNKNOWN,
+                    ..
+                })
+            ),
+            "Should have been `UNKNOWN` but was `{res_unknown:?}`"
+        );
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
@@ -0,0 +1,246 @@
+//! Typed builder of the `ParamsList<F>` of the list rpc methods
+//! (e.g., `list_tasks` with `ListParams<TaskFilter>`).
+//!
+//! Note: The modql filters and list options are deserialize only, so the
+//!       builder produces their json form, which is checked against
+//!       `ParamsList<F>` before being sent (e.g., a value of the wrong type).
+//!
+//! ```ignore
+//! let params = ListParams::<TaskFilter>::new()
+//!     .filter(FilterGroup::new().ends_with("title", "BB").eq("done", false))
+//!     .order_by("id", true)
+//!     .limit(10);
+//! let tasks = client.list_tasks(params).await?;
+//! ```
+
+use crate::{Error, Result};
+use lib_rpc::ParamsList;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::marker::PhantomData;
+
+/// The filters (OR-ed groups) and list options of a list rpc method.
+pub struct ListParams<F> {
+    filters: Vec<FilterGroup>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    order_bys: Vec<String>,
+    _filter: PhantomData<F>,
+}
+
+impl<F: DeserializeOwned> Default for ListParams<F> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<F: DeserializeOwned> ListParams<F> {
+    pub fn new() -> Self {
+        ListParams {
+            filters: Vec::new(),
+            limit: None,
+            offset: None,
+            order_bys: Vec::new(),
+            _filter: PhantomData,
+        }
+    }
+
+    /// Add a filter group, the items matching any of the groups being listed.
+    pub fn filter(mut self, group: FilterGroup) -> Self {
+        self.filters.push(group);
+        self
+    }
+
+    pub fn limit(mut self, limit: i64) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    pub fn offset(mut self, offset: i64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Order by the `field` (then by the next `order_by` fields).
+    pub fn order_by(mut self, field: &str, desc: bool) -> Self {
+        let order_by = if desc {
+            format!("!{field}")
+        } else {
+            field.to_string()
+        };
+        self.order_bys.push(order_by);
+        self
+    }
+
+    /// The params json, once checked against `ParamsList<F>`.
+    pub(crate) fn into_json(self, rpc_method: &str) -> Result<Value> {
+        let filters: Vec<Value> = self
+            .filters
+            .into_iter()
+            .map(|group| Value::Object(group.0))
+            .collect();
+
+        let mut list_options = Map::new();
+        if let Some(limit) = self.limit {
+            list_options.insert("limit".to_string(), limit.into());
+        }
+        if let Some(offset) = self.offset {
+            list_options.insert("offset".to_string(), offset.into());
+        }
+        if !self.order_bys.is_empty() {
+            list_options.insert("order_bys".to_string(), self.order_bys.into());
+        }
+
+        let params = json!({
+            "filters": (!filters.is_empty()).then_some(filters),
+            "list_options": (!list_options.is_empty()).then_some(list_options),
+        });
+
+        serde_path_to_error::deserialize::<_, ParamsList<F>>(&params).map_err(
+            |ex| Error::RpcParamsInvalid {
+                rpc_method: rpc_method.to_string(),
+                param_path: ex.path().to_string(),
+                cause: ex.inner().to_string(),
+            },
+        )?;
+
+        Ok(params)
+    }
+}
+
+/// The field conditions of a filter, all of which must match
+/// (e.g., `FilterGroup::new().eq("done", false).contains("title", "BB")`).
+#[derive(Default)]
+pub struct FilterGroup(Map<String, Value>);
+
+impl FilterGroup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn eq(self, field: &str, value: impl Into<Value>) -> Self {
+        self.op(field, "$eq", value.into())
+    }
+
+    pub fn not(self, field: &str, value: impl Into<Value>) -> Self {
+        self.op(field, "$not", value.into())
+    }
+
+    pub fn is_in<V: Into<Value>>(self, field: &str, values: Vec<V>) -> Self {
+        self.op(field, "$in", values.into())
+    }
+
+    pub fn not_in<V: Into<Value>>(self, field: &str, values: Vec<V>) -> Self {
+        self.op(field, "$notIn", values.into())
+    }
+
+    pub fn lt(self, field: &str, value: impl Into<Value>) -> Self {
+        self.op(field, "$lt", value.into())
+    }
+
+    pub fn lte(self, field: &str, value: impl Into<Value>) -> Self {
+        self.op(field, "$lte", value.into())
+    }
+
+    pub fn gt(self, field: &str, value: impl Into<Value>) -> Self {
+        self.op(field, "$gt", value.into())
+    }
+
+    pub fn gte(self, field: &str, value: impl Into<Value>) -> Self {
+        self.op(field, "$gte", value.into())
+    }
+
+    pub fn contains(self, field: &str, value: &str) -> Self {
+        self.op(field, "$contains", value.into())
+    }
+
+    pub fn starts_with(self, field: &str, value: &str) -> Self {
+        self.op(field, "$startsWith", value.into())
+    }
+
+    pub fn ends_with(self, field: &str, value: &str) -> Self {
+        self.op(field, "$endsWith", value.into())
+    }
+
+    pub fn null(self, field: &str, is_null: bool) -> Self {
+        self.op(field, "$null", is_null.into())
+    }
+
+    /// Add the `op` condition to the (other) conditions of the `field`.
+    fn op(mut self, field: &str, op: &str, value: Value) -> Self {
+        let ops = self
+            .0
+            .entry(field.to_string())
+            .or_insert_with(|| Value::Object(Map::new()));
+        if let Value::Object(ops) = ops {
+            ops.insert(op.to_string(), value);
+        }
+        self
+    }
+}
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use lib_core::model::task::TaskFilter;
+
+    #[test]
+    fn test_list_params_into_json_ok() -> Result<()> {
+        // -- Fixtures
+        let fx_params = ListParams::<TaskFilter>::new()
+            .filter(
+                FilterGroup::new()
+                    .ends_with("title", "BB")
+                    .contains("title", "task")
+                    .eq("done", false),
+            )
+            .filter(FilterGroup::new().is_in("id", vec![1000, 1001]))
+            .order_by("id", true)
+            .limit(10);
+
+        // -- Exec
+        let params = fx_params.into_json("list_tasks")?;
+
+        // -- Check
+        assert_eq!(
+            params,
+            json!({
+                "filters": [
+                    {
+                        "title": {"$endsWith": "BB", "$contains": "task"},
+                        "done": {"$eq": false}
+                    },
+                    {"id": {"$in": [1000, 1001]}}
+                ],
+                "list_options": {"limit": 10, "order_bys": ["!id"]}
+            })
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_params_into_json_err_value_type() -> Result<()> {
+        // -- Fixtures
+        let fx_params = ListParams::<TaskFilter>::new()
+            .filter(FilterGroup::new().eq("done", "no"));
+
+        // -- Exec
+        let res = fx_params.into_json("list_tasks");
+
+        // -- Check
+        assert!(
+            matches!(
+                &res,
+                Err(Error::RpcParamsInvalid { rpc_method, .. })
+                    if rpc_method == "list_tasks"
+            ),
+            "Should have been `RpcParamsInvalid` but was `{res:?}`"
+        );
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
@@ -0,0 +1,33 @@
+use crate::{ListParams, Result, RpcClient};
+use lib_core::model::task::{Task, TaskFilter, TaskForCreate, TaskForUpdate};
+use lib_rpc::{ParamsForCreate, ParamsForUpdate, ParamsIded};
+
+impl RpcClient {
+    pub async fn create_task(&self, task_c: TaskForCreate) -> Result<Task> {
+        let params = ParamsForCreate { data: task_c };
+
+        self.call("create_task", Some(params)).await
+    }
+
+    /// List the tasks matching the filters of the `params`
+    /// (e.g., `ListParams::new().filter(FilterGroup::new().eq("done", false))`).
+    pub async fn list_tasks(
+        &self,
+        params: ListParams<TaskFilter>,
+    ) -> Result<Vec<Task>> {
+        let rpc_method = "list_tasks";
+        let params = params.into_json(rpc_method)?;
+
+        self.call(rpc_method, Some(params)).await
+    }
+
+    pub async fn update_task(&self, id: i64, task_u: TaskForUpdate) -> Result<Task> {
+        let params = ParamsForUpdate { id, data: task_u };
+
+        self.call("update_task", Some(params)).await
+    }
+
+    pub async fn delete_task(&self, id: i64) -> Result<Task> {
+        self.call("delete_task", Some(ParamsIded { id })).await
+    }
+}
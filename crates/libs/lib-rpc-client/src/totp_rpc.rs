@@ -0,0 +1,18 @@
+use crate::{Result, RpcClient};
+use lib_core::model::user::TotpEnrollment;
+use lib_rpc::ParamsCode;
+
+impl RpcClient {
+    pub async fn totp_enroll(&self) -> Result<TotpEnrollment> {
+        self.call::<(), _>("totp_enroll", None).await
+    }
+
+    /// Returns the recovery codes (only given once).
+    pub async fn totp_confirm(&self, code: &str) -> Result<Vec<String>> {
+        let params = ParamsCode {
+            code: code.to_string(),
+        };
+
+        self.call("totp_confirm", Some(params)).await
+    }
+}
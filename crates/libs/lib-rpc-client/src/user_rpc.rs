@@ -0,0 +1,99 @@
+use crate::{ListParams, Result, RpcClient};
+use lib_core::model::user::{
+    User, UserFilter, UserForProfileUpdate, UserForUpdate, UserProfile,
+};
+use lib_rpc::{
+    Impersonation, ParamsChangeEmail, ParamsChangePwd, ParamsData, ParamsForUpdate,
+    ParamsIded,
+};
+use serde_json::Value;
+
+impl RpcClient {
+    /// The profile of the logged in user.
+    pub async fn whoami(&self) -> Result<UserProfile> {
+        self.call::<(), _>("whoami", None).await
+    }
+
+    /// Update the profile of the logged in user (the `None` fields are
+    /// unchanged).
+    pub async fn update_my_profile(
+        &self,
+        profile_u: UserForProfileUpdate,
+    ) -> Result<UserProfile> {
+        let params = ParamsData { data: profile_u };
+
+        self.call("update_my_profile", Some(params)).await
+    }
+
+    /// Note: All of the user sessions (including this client one) have to
+    ///       `login` again after the change.
+    pub async fn change_pwd(&self, pwd_current: &str, pwd_new: &str) -> Result<()> {
+        let params = ParamsChangePwd {
+            pwd_current: pwd_current.to_string(),
+            pwd_clear: pwd_new.to_string(),
+        };
+        self.call::<_, Value>("change_pwd", Some(params)).await?;
+
+        Ok(())
+    }
+
+    /// Change the email (where the pwd reset mails go) of the logged in user,
+    /// which requires its current password.
+    pub async fn change_email(
+        &self,
+        pwd_current: &str,
+        email: &str,
+    ) -> Result<UserProfile> {
+        let params = ParamsChangeEmail {
+            pwd_current: pwd_current.to_string(),
+            email: email.to_string(),
+        };
+
+        self.call("change_email", Some(params)).await
+    }
+
+    // -- User management (for the admins).
+
+    pub async fn get_user(&self, id: i64) -> Result<User> {
+        self.call("get_user", Some(ParamsIded { id })).await
+    }
+
+    /// List the users matching the filters of the `params`
+    /// (e.g., `ListParams::new().filter(FilterGroup::new().eq("disabled", true))`).
+    pub async fn list_users(
+        &self,
+        params: ListParams<UserFilter>,
+    ) -> Result<Vec<User>> {
+        let rpc_method = "list_users";
+        let params = params.into_json(rpc_method)?;
+
+        self.call(rpc_method, Some(params)).await
+    }
+
+    pub async fn update_user(&self, id: i64, user_u: UserForUpdate) -> Result<User> {
+        let params = ParamsForUpdate { id, data: user_u };
+
+        self.call("update_user", Some(params)).await
+    }
+
+    pub async fn disable_user(&self, id: i64) -> Result<User> {
+        self.call("disable_user", Some(ParamsIded { id })).await
+    }
+
+    pub async fn enable_user(&self, id: i64) -> Result<User> {
+        self.call("enable_user", Some(ParamsIded { id })).await
+    }
+
+    /// Start acting as the user. The returned `token` is for another client
+    /// (see `with_bearer_token`), this one keeping the admin session.
+    pub async fn impersonate_user(&self, id: i64) -> Result<Impersonation> {
+        self.call("impersonate_user", Some(ParamsIded { id })).await
+    }
+
+    /// Stop the impersonation (from the admin or the impersonation client).
+    pub async fn stop_impersonation(&self) -> Result<()> {
+        self.call::<(), Value>("stop_impersonation", None).await?;
+
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use derive_more::From;
+use lib_auth::token;
 use lib_core::model;
+use lib_utils::validate::FieldError;
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
 
@@ -14,11 +16,35 @@ pub enum Error {
     },
     RpcFailJsonParams {
         rpc_method: String,
+        param_path: String,
+        cause: String,
+    },
+    RpcParamsInvalid {
+        rpc_method: String,
+        field_errors: Vec<FieldError>,
+    },
+
+    // -- Access
+    AdminRequired {
+        user_id: i64,
+    },
+
+    // -- Impersonation
+    /// The rpc method is not allowed while impersonating (e.g., `change_pwd`).
+    ImpersonationBlocked {
+        rpc_method: String,
+        impersonator_id: i64,
+    },
+    /// Only the enabled, non admin, other users can be impersonated.
+    ImpersonationTargetInvalid {
+        user_id: i64,
     },
 
     // -- Modules
     #[from]
     Model(model::Error),
+    #[from]
+    Token(token::Error),
 
     // -- External Modules
     #[from]
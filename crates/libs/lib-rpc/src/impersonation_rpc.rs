@@ -0,0 +1,66 @@
+use crate::user_rpc::require_admin;
+use crate::{Error, ParamsIded, Result};
+use lib_auth::token::generate_impersonation_token;
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{User, UserBmc, UserForAuth};
+use lib_core::model::ModelManager;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+/// The impersonation token, to be given as the `Authorization: Bearer` token of
+/// the requests acting as the user (the admin session being unchanged).
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Impersonation {
+    pub token: String,
+    pub user: User,
+}
+
+/// Start acting as the user, with a new impersonation token.
+///
+/// Note: Ends the previous impersonation of the admin, if any.
+pub async fn impersonate_user(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<Impersonation> {
+    require_admin(&ctx)?;
+    let ParamsIded { id } = params;
+
+    let admin: UserForAuth = UserBmc::get(&ctx, &mm, ctx.user_id()).await?;
+    let target: UserForAuth = UserBmc::get(&ctx, &mm, id).await?;
+    if target.id == admin.id || target.is_admin || target.disabled {
+        return Err(Error::ImpersonationTargetInvalid { user_id: id });
+    }
+
+    UserBmc::set_impersonating(&ctx, &mm, admin.id, Some(target.id)).await?;
+    let token = generate_impersonation_token(
+        &admin.username,
+        admin.token_salt,
+        &target.username,
+        target.token_salt,
+    )?;
+    info!(
+        "{:<12} - start - admin: {}, user: {}",
+        "IMPERSONATION", admin.id, target.id
+    );
+
+    let user = UserBmc::get(&ctx, &mm, id).await?;
+
+    Ok(Impersonation {
+        token: token.to_string(),
+        user,
+    })
+}
+
+/// Stop the impersonation of the real user, whether called with the
+/// impersonation token or the admin session.
+///
+/// Note: The impersonation tokens are then rejected.
+pub async fn stop_impersonation(ctx: Ctx, mm: ModelManager) -> Result<()> {
+    let admin_id = ctx.real_user_id();
+
+    UserBmc::set_impersonating(&ctx, &mm, admin_id, None).await?;
+    info!("{:<12} - stop - admin: {admin_id}", "IMPERSONATION");
+
+    Ok(())
+}
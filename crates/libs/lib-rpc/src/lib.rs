@@ -1,20 +1,69 @@
 // region:    --- Modules
 
 mod error;
+mod impersonation_rpc;
 mod params;
 mod task_rpc;
+mod totp_rpc;
+mod user_rpc;
 
 pub use self::error::{Error, Result};
-use params::*;
+pub use impersonation_rpc::Impersonation;
+pub use params::*;
 
+use impersonation_rpc::{impersonate_user, stop_impersonation};
 use lib_core::ctx::Ctx;
 use lib_core::model::ModelManager;
+use lib_utils::validate::{self, Validate};
 use serde::Deserialize;
-use serde_json::{from_value, to_value, Value};
+use serde_json::{to_value, Value};
 use task_rpc::{create_task, delete_task, list_tasks, update_task};
+use totp_rpc::{totp_confirm, totp_enroll};
+use user_rpc::{
+	change_email, change_pwd, disable_user, enable_user, get_user, list_users,
+	update_my_profile, update_user, whoami,
+};
+use tracing::instrument;
 
 // endregion: --- Modules
 
+/// The rpc methods not allowed while impersonating, i.e., the ones changing the
+/// user credentials, email or profile, and the nested impersonation.
+const IMPERSONATION_BLOCKED_METHODS: &[&str] = &[
+	"change_pwd",
+	"change_email",
+	"update_my_profile",
+	"totp_enroll",
+	"totp_confirm",
+	"impersonate_user",
+];
+
+/// The rpc methods checking the current password, i.e., to be throttled as
+/// the logins are (see the web-server `login_guard`).
+pub const PWD_CHECK_METHODS: &[&str] = &["change_pwd", "change_email"];
+
+/// The rpc methods served by `exec_rpc` (e.g., for the metrics labels,
+/// which should not take the unknown methods sent by the clients).
+pub const RPC_METHODS: &[&str] = &[
+	"create_task",
+	"list_tasks",
+	"update_task",
+	"delete_task",
+	"whoami",
+	"update_my_profile",
+	"change_pwd",
+	"change_email",
+	"get_user",
+	"list_users",
+	"update_user",
+	"disable_user",
+	"enable_user",
+	"impersonate_user",
+	"stop_impersonation",
+	"totp_enroll",
+	"totp_confirm",
+];
+
 // region:    --- RPC Types
 
 /// The raw JSON-RPC request object, serving as the foundation for RPC routing.
@@ -32,9 +81,16 @@ macro_rules! exec_rpc_fn {
 		let params = $rpc_params.ok_or(Error::RpcMissingParams {
 			rpc_method: rpc_fn_name.to_string(),
 		})?;
-		let params = from_value(params).map_err(|_| Error::RpcFailJsonParams {
-			rpc_method: rpc_fn_name.to_string(),
+		// Note: serde_path_to_error keeps track of the failing param path
+		//       (e.g., `data.title`) so that it can be returned to the client.
+		let params = serde_path_to_error::deserialize(params).map_err(|ex| {
+			Error::RpcFailJsonParams {
+				rpc_method: rpc_fn_name.to_string(),
+				param_path: ex.path().to_string(),
+				cause: ex.inner().to_string(),
+			}
 		})?;
+		let params = validate_params(rpc_fn_name, params)?;
 		$rpc_fn($ctx, $mm, params).await.map(to_value)?? // FIXME
 	}};
 
@@ -44,6 +100,31 @@ macro_rules! exec_rpc_fn {
 	};
 }
 
+/// Validate the rpc params before they are given to the rpc handler.
+///
+/// Note: This is a generic function rather than a method call in `exec_rpc_fn!`
+///       so that the params type can still be inferred from the rpc handler.
+fn validate_params<P: Validate>(rpc_method: &str, params: P) -> Result<P> {
+	match params.validate() {
+		Ok(()) => Ok(params),
+		Err(validate::Error::FieldsInvalid(field_errors)) => {
+			Err(Error::RpcParamsInvalid {
+				rpc_method: rpc_method.to_string(),
+				field_errors,
+			})
+		}
+	}
+}
+
+#[instrument(
+	skip_all,
+	fields(
+		rpc_method = %rpc_req.method,
+		req_id = ?ctx.req_id(),
+		user_id = ctx.user_id(),
+		impersonator_id = ?ctx.impersonator_id()
+	)
+)]
 pub async fn exec_rpc(
 	ctx: Ctx,
 	mm: ModelManager,
@@ -52,7 +133,18 @@ pub async fn exec_rpc(
 	let rpc_method = rpc_req.method;
 	let rpc_params = rpc_req.params;
 
+	// -- Check the impersonation restrictions.
+	if let Some(impersonator_id) = ctx.impersonator_id() {
+		if IMPERSONATION_BLOCKED_METHODS.contains(&rpc_method.as_str()) {
+			return Err(Error::ImpersonationBlocked {
+				rpc_method,
+				impersonator_id,
+			});
+		}
+	}
+
 	// -- Exec & Store RpcInfo in response.
+	// Note: The methods added here should also be added to `RPC_METHODS`.
 	let result_json: Value = match rpc_method.as_str() {
 		// -- Task RPC methods.
 		"create_task" => exec_rpc_fn!(create_task, ctx, mm, rpc_params),
@@ -60,6 +152,25 @@ pub async fn exec_rpc(
 		"update_task" => exec_rpc_fn!(update_task, ctx, mm, rpc_params),
 		"delete_task" => exec_rpc_fn!(delete_task, ctx, mm, rpc_params),
 
+		// -- User RPC methods (for the ctx user).
+		"whoami" => exec_rpc_fn!(whoami, ctx, mm),
+		"update_my_profile" => exec_rpc_fn!(update_my_profile, ctx, mm, rpc_params),
+		"change_pwd" => exec_rpc_fn!(change_pwd, ctx, mm, rpc_params),
+		"change_email" => exec_rpc_fn!(change_email, ctx, mm, rpc_params),
+
+		// -- User RPC methods (for the admins).
+		"get_user" => exec_rpc_fn!(get_user, ctx, mm, rpc_params),
+		"list_users" => exec_rpc_fn!(list_users, ctx, mm, rpc_params),
+		"update_user" => exec_rpc_fn!(update_user, ctx, mm, rpc_params),
+		"disable_user" => exec_rpc_fn!(disable_user, ctx, mm, rpc_params),
+		"enable_user" => exec_rpc_fn!(enable_user, ctx, mm, rpc_params),
+		"impersonate_user" => exec_rpc_fn!(impersonate_user, ctx, mm, rpc_params),
+		"stop_impersonation" => exec_rpc_fn!(stop_impersonation, ctx, mm),
+
+		// -- Totp RPC methods (for the ctx user).
+		"totp_enroll" => exec_rpc_fn!(totp_enroll, ctx, mm),
+		"totp_confirm" => exec_rpc_fn!(totp_confirm, ctx, mm, rpc_params),
+
 		// -- Fallback as Err.
 		_ => return Err(Error::RpcMethodUnknown(rpc_method)),
 	};
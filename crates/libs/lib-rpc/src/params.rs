@@ -4,30 +4,64 @@
 //! Most of these base constructs use generics for their respective data elements, allowing
 //! each rpc handler function to receive the exact desired type.
 //!
+//! Note: They are also used by `lib-rpc-client` to build the rpc requests
+//!       (hence the `Serialize`, except for `ParamsList`, since the modql
+//!       filters and list options are deserialize only).
+//!
 
+use lib_utils::validate::{self, StrRule, Validate, Validator};
 use modql::filter::ListOptions;
 use serde::de::DeserializeOwned;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, OneOrMany};
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct ParamsForCreate<D> {
     pub data: D,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 pub struct ParamsForUpdate<D> {
     pub id: i64,
     pub data: D,
 }
 
-#[derive(Deserialize)]
+/// The data only, for the updates of the ctx user (i.e., without id).
+#[derive(Serialize, Deserialize)]
+pub struct ParamsData<D> {
+    pub data: D,
+}
+
+#[derive(Serialize, Deserialize)]
 pub struct ParamsIded {
     pub id: i64,
 }
 
+/// A one-time code (e.g., totp code or recovery code).
+#[derive(Serialize, Deserialize)]
+pub struct ParamsCode {
+    pub code: String,
+}
+
+/// Note: The new password is validated by `UserBmc::change_pwd`.
+#[derive(Serialize, Deserialize)]
+pub struct ParamsChangePwd {
+    pub pwd_current: String,
+    pub pwd_clear: String,
+}
+
+/// Note: The new email is validated by `UserBmc::change_email`.
+#[derive(Serialize, Deserialize)]
+pub struct ParamsChangeEmail {
+    pub pwd_current: String,
+    pub email: String,
+}
+
+// Note: The explicit bound, since the serde_as `Option` default would
+//       otherwise require `F: Default`.
 #[serde_as]
 #[derive(Deserialize)]
+#[serde(bound = "F: DeserializeOwned")]
 pub struct ParamsList<F>
 where
     F: DeserializeOwned,
@@ -36,3 +70,71 @@ where
     pub filters: Option<Vec<F>>,
     pub list_options: Option<ListOptions>,
 }
+
+// region:    --- Params Validation
+
+// Note: The params validation delegates to the `data` validation, prefixing the
+//       field errors with `data.` so that they match the params json path.
+
+impl<D: Validate> Validate for ParamsForCreate<D> {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new().nested("data", &self.data).finish()
+    }
+}
+
+impl<D: Validate> Validate for ParamsForUpdate<D> {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new().nested("data", &self.data).finish()
+    }
+}
+
+impl<D: Validate> Validate for ParamsData<D> {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new().nested("data", &self.data).finish()
+    }
+}
+
+impl Validate for ParamsIded {
+    fn validate(&self) -> validate::Result<()> {
+        Ok(())
+    }
+}
+
+impl Validate for ParamsCode {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check(
+                "code",
+                &self.code,
+                &[StrRule::NotBlank, StrRule::MaxLen(32)],
+            )
+            .finish()
+    }
+}
+
+impl Validate for ParamsChangePwd {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("pwd_current", &self.pwd_current, &[StrRule::NotBlank])
+            .finish()
+    }
+}
+
+impl Validate for ParamsChangeEmail {
+    fn validate(&self) -> validate::Result<()> {
+        Validator::new()
+            .check("pwd_current", &self.pwd_current, &[StrRule::NotBlank])
+            .finish()
+    }
+}
+
+impl<F> Validate for ParamsList<F>
+where
+    F: DeserializeOwned,
+{
+    fn validate(&self) -> validate::Result<()> {
+        Ok(())
+    }
+}
+
+// endregion: --- Params Validation
@@ -0,0 +1,25 @@
+use crate::ParamsCode;
+use crate::Result;
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{TotpEnrollment, UserBmc};
+use lib_core::model::ModelManager;
+
+pub async fn totp_enroll(ctx: Ctx, mm: ModelManager) -> Result<TotpEnrollment> {
+    let enrollment = UserBmc::totp_enroll(&ctx, &mm, ctx.user_id()).await?;
+
+    Ok(enrollment)
+}
+
+/// Returns the recovery codes (only given once).
+pub async fn totp_confirm(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsCode,
+) -> Result<Vec<String>> {
+    let ParamsCode { code } = params;
+
+    let recovery_codes =
+        UserBmc::totp_confirm(&ctx, &mm, ctx.user_id(), &code).await?;
+
+    Ok(recovery_codes)
+}
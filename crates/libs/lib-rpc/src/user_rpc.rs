@@ -0,0 +1,154 @@
+use crate::{Error, Result};
+use crate::{
+    ParamsChangeEmail, ParamsChangePwd, ParamsData, ParamsForUpdate, ParamsIded,
+    ParamsList,
+};
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{
+    User, UserBmc, UserFilter, UserForProfileUpdate, UserForUpdate, UserProfile,
+};
+use lib_core::model::ModelManager;
+
+// region:    --- Ctx User
+
+pub async fn whoami(ctx: Ctx, mm: ModelManager) -> Result<UserProfile> {
+    let profile = UserBmc::get(&ctx, &mm, ctx.user_id()).await?;
+
+    Ok(profile)
+}
+
+pub async fn update_my_profile(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsData<UserForProfileUpdate>,
+) -> Result<UserProfile> {
+    let ParamsData { data } = params;
+
+    UserBmc::update_profile(&ctx, &mm, ctx.user_id(), data).await?;
+
+    let profile = UserBmc::get(&ctx, &mm, ctx.user_id()).await?;
+
+    Ok(profile)
+}
+
+/// Note: All of the user sessions (including the current one) have to
+///       log in again after the change.
+pub async fn change_pwd(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsChangePwd,
+) -> Result<()> {
+    let ParamsChangePwd {
+        pwd_current,
+        pwd_clear,
+    } = params;
+
+    UserBmc::change_pwd(&ctx, &mm, ctx.user_id(), &pwd_current, &pwd_clear).await?;
+
+    Ok(())
+}
+
+/// Note: Requires the current password, the email being where the pwd reset
+///       mails go (see `UserBmc::change_email`).
+pub async fn change_email(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsChangeEmail,
+) -> Result<UserProfile> {
+    let ParamsChangeEmail { pwd_current, email } = params;
+
+    UserBmc::change_email(&ctx, &mm, ctx.user_id(), &pwd_current, &email).await?;
+
+    let profile = UserBmc::get(&ctx, &mm, ctx.user_id()).await?;
+
+    Ok(profile)
+}
+
+// endregion: --- Ctx User
+
+// region:    --- User Management
+
+pub async fn get_user(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<User> {
+    require_admin(&ctx)?;
+    let ParamsIded { id } = params;
+
+    let user = UserBmc::get(&ctx, &mm, id).await?;
+
+    Ok(user)
+}
+
+pub async fn list_users(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsList<UserFilter>,
+) -> Result<Vec<User>> {
+    require_admin(&ctx)?;
+
+    let users =
+        UserBmc::list(&ctx, &mm, params.filters, params.list_options).await?;
+
+    Ok(users)
+}
+
+pub async fn update_user(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsForUpdate<UserForUpdate>,
+) -> Result<User> {
+    require_admin(&ctx)?;
+    let ParamsForUpdate { id, data } = params;
+
+    UserBmc::update(&ctx, &mm, id, data).await?;
+
+    let user = UserBmc::get(&ctx, &mm, id).await?;
+
+    Ok(user)
+}
+
+/// Note: The user sessions end on their next request
+///       (or next check, for the websocket ones).
+pub async fn disable_user(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<User> {
+    require_admin(&ctx)?;
+    let ParamsIded { id } = params;
+
+    UserBmc::set_disabled(&ctx, &mm, id, true).await?;
+
+    let user = UserBmc::get(&ctx, &mm, id).await?;
+
+    Ok(user)
+}
+
+pub async fn enable_user(
+    ctx: Ctx,
+    mm: ModelManager,
+    params: ParamsIded,
+) -> Result<User> {
+    require_admin(&ctx)?;
+    let ParamsIded { id } = params;
+
+    UserBmc::set_disabled(&ctx, &mm, id, false).await?;
+
+    let user = UserBmc::get(&ctx, &mm, id).await?;
+
+    Ok(user)
+}
+
+pub(crate) fn require_admin(ctx: &Ctx) -> Result<()> {
+    if ctx.is_admin() {
+        Ok(())
+    } else {
+        Err(Error::AdminRequired {
+            user_id: ctx.user_id(),
+        })
+    }
+}
+
+// endregion: --- User Management
@@ -0,0 +1,335 @@
+//! Layered config loader.
+//!
+//! Each setting is named by its env name (e.g., `SERVICE_DB_URL`), and its value
+//! is taken from the first layer which has it:
+//!
+//! 1. `{NAME}_FILE` env, the path of a file holding the value
+//!    (e.g., docker or kubernetes secrets).
+//! 2. `{NAME}` env.
+//! 3. The toml file at `SERVICE_CONFIG_FILE`, keyed by the lower case name
+//!    without the `SERVICE_` prefix (e.g., `db_url = "..."`).
+//! 4. The defaults given to the `ConfigReader`.
+//!
+//! A `ConfigReader` collects all of the missing or invalid settings (rather than
+//! stopping at the first one), so that they can be reported at once.
+//!
+//! ```ignore
+//! let mut reader = ConfigReader::new(&[("SERVICE_TOKEN_DURATION_SEC", "1800")]);
+//! let config = AuthConfig {
+//!     TOKEN_KEY: reader.get_b64u("SERVICE_TOKEN_KEY"),
+//!     TOKEN_DURATION_SEC: reader.get("SERVICE_TOKEN_DURATION_SEC"),
+//! };
+//! reader.finish()?;
+//! ```
+//!
+
+use crate::b64::b64u_decode;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+const CONFIG_FILE_ENV: &str = "SERVICE_CONFIG_FILE";
+const NAME_PREFIX: &str = "SERVICE_";
+const FILE_SUFFIX: &str = "_FILE";
+
+// region:    --- ConfigReader
+
+pub struct ConfigReader {
+    defaults: HashMap<&'static str, &'static str>,
+    errors: Vec<SettingError>,
+}
+
+impl ConfigReader {
+    pub fn new(defaults: &[(&'static str, &'static str)]) -> Self {
+        ConfigReader {
+            defaults: defaults.iter().copied().collect(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Returns the parsed value of a required setting.
+    ///
+    /// Note: When missing or invalid, the error is recorded and the type default
+    ///       is returned (the config is then rejected by `finish`).
+    pub fn get<T: FromStr + Default>(&mut self, name: &'static str) -> T {
+        match self.raw(name) {
+            Some(value) => self.parse(name, &value).unwrap_or_default(),
+            None => {
+                self.errors.push(SettingError::Missing(name.to_string()));
+                T::default()
+            }
+        }
+    }
+
+    /// Returns the parsed value of an optional setting
+    /// (only recording an error when it is invalid).
+    pub fn get_opt<T: FromStr>(&mut self, name: &'static str) -> Option<T> {
+        let value = self.raw(name)?;
+        self.parse(name, &value)
+    }
+
+    /// Returns the bytes of a required base64url setting (e.g., keys).
+    pub fn get_b64u(&mut self, name: &'static str) -> Vec<u8> {
+        let value: String = self.get(name);
+        if value.is_empty() {
+            return Vec::new();
+        }
+
+        b64u_decode(&value).unwrap_or_else(|_| {
+            self.errors.push(SettingError::WrongFormat(name.to_string()));
+            Vec::new()
+        })
+    }
+
+    /// Record an error found by the caller (e.g., inconsistent settings).
+    pub fn push_error(&mut self, error: SettingError) {
+        self.errors.push(error);
+    }
+
+    pub fn finish(self) -> Result<()> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::SettingsInvalid(self.errors))
+        }
+    }
+
+    /// Returns the raw value from the first layer which has it.
+    fn raw(&mut self, name: &'static str) -> Option<String> {
+        // -- Secret file.
+        let file_env = format!("{name}{FILE_SUFFIX}");
+        if let Some(path) = env_opt(&file_env) {
+            return match fs::read_to_string(&path) {
+                Ok(content) => Some(content.trim().to_string()),
+                Err(ex) => {
+                    self.errors.push(SettingError::FileUnreadable {
+                        name: file_env,
+                        path,
+                        cause: ex.to_string(),
+                    });
+                    None
+                }
+            };
+        }
+
+        // -- Env.
+        if let Some(value) = env_opt(name) {
+            return Some(value);
+        }
+
+        // -- Config file.
+        match config_file() {
+            Ok(values) => {
+                if let Some(value) = values.get(name) {
+                    return Some(value.clone());
+                }
+            }
+            Err(error) => {
+                if !self.errors.contains(error) {
+                    self.errors.push(error.clone());
+                }
+            }
+        }
+
+        // -- Defaults.
+        self.defaults.get(name).map(|value| value.to_string())
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &'static str, value: &str) -> Option<T> {
+        let parsed = value.parse::<T>().ok();
+        if parsed.is_none() {
+            self.errors.push(SettingError::WrongFormat(name.to_string()));
+        }
+        parsed
+    }
+}
+
+/// Returns `None` when the env is not set (or empty).
+fn env_opt(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+// endregion: --- ConfigReader
+
+// region:    --- Config File
+
+/// The config file values by setting name (loaded once).
+fn config_file() -> &'static core::result::Result<HashMap<String, String>, SettingError> {
+    static INSTANCE: OnceLock<
+        core::result::Result<HashMap<String, String>, SettingError>,
+    > = OnceLock::new();
+
+    INSTANCE.get_or_init(|| {
+        let Some(path) = env_opt(CONFIG_FILE_ENV) else {
+            return Ok(HashMap::new());
+        };
+
+        let content =
+            fs::read_to_string(&path).map_err(|ex| SettingError::FileUnreadable {
+                name: CONFIG_FILE_ENV.to_string(),
+                path: path.clone(),
+                cause: ex.to_string(),
+            })?;
+
+        parse_config_file(&content).map_err(|cause| SettingError::FileInvalid {
+            path,
+            cause,
+        })
+    })
+}
+
+fn parse_config_file(
+    content: &str,
+) -> core::result::Result<HashMap<String, String>, String> {
+    let table: toml::Table = content.parse().map_err(|ex: toml::de::Error| {
+        ex.to_string()
+    })?;
+
+    let mut values = HashMap::new();
+    for (key, value) in table {
+        let value = match value {
+            toml::Value::String(value) => value,
+            toml::Value::Integer(value) => value.to_string(),
+            toml::Value::Float(value) => value.to_string(),
+            toml::Value::Boolean(value) => value.to_string(),
+            _ => return Err(format!("'{key}' must be a string, number or bool")),
+        };
+        values.insert(format!("{NAME_PREFIX}{}", key.to_uppercase()), value);
+    }
+
+    Ok(values)
+}
+
+// endregion: --- Config File
+
+// region:    --- Error
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SettingError {
+    Missing(String),
+    WrongFormat(String),
+    FileUnreadable {
+        name: String,
+        path: String,
+        cause: String,
+    },
+    FileInvalid {
+        path: String,
+        cause: String,
+    },
+}
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+    SettingsInvalid(Vec<SettingError>),
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(
+        &self,
+        fmt: &mut core::fmt::Formatter,
+    ) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
+
+// endregion: --- Error
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_reader_layers_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_secret_path = env::temp_dir().join("test_reader_layers_ok-secret");
+        fs::write(&fx_secret_path, "secret-from-file\n")?;
+        env::set_var("TEST_READER_LAYERS_ENV", "42");
+        env::set_var("TEST_READER_LAYERS_SECRET", "secret-from-env");
+        env::set_var("TEST_READER_LAYERS_SECRET_FILE", &fx_secret_path);
+        let mut reader = ConfigReader::new(&[
+            ("TEST_READER_LAYERS_DEFAULT", "default"),
+            ("TEST_READER_LAYERS_ENV", "1"),
+        ]);
+
+        // -- Exec
+        let default_value: String = reader.get("TEST_READER_LAYERS_DEFAULT");
+        let env_value: u64 = reader.get("TEST_READER_LAYERS_ENV");
+        let secret_value: String = reader.get("TEST_READER_LAYERS_SECRET");
+        let opt_value: Option<String> = reader.get_opt("TEST_READER_LAYERS_NONE");
+
+        // -- Check
+        reader.finish()?;
+        assert_eq!(default_value, "default");
+        assert_eq!(env_value, 42);
+        assert_eq!(secret_value, "secret-from-file");
+        assert_eq!(opt_value, None);
+
+        // -- Clean
+        fs::remove_file(fx_secret_path)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reader_err_all_settings() -> Result<()> {
+        // -- Setup & Fixtures
+        env::set_var("TEST_READER_ERR_NUM", "not-a-number");
+        let mut reader = ConfigReader::new(&[]);
+
+        // -- Exec
+        let _: String = reader.get("TEST_READER_ERR_MISSING");
+        let _: u64 = reader.get("TEST_READER_ERR_NUM");
+        let res = reader.finish();
+
+        // -- Check
+        let Err(Error::SettingsInvalid(errors)) = res else {
+            panic!("Should have been `Err(Error::SettingsInvalid(..))` but was `{res:?}`");
+        };
+        assert_eq!(
+            errors,
+            [
+                SettingError::Missing("TEST_READER_ERR_MISSING".to_string()),
+                SettingError::WrongFormat("TEST_READER_ERR_NUM".to_string()),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_config_file_ok() -> Result<()> {
+        // -- Fixtures
+        let fx_content = r#"
+            db_url = "postgres://localhost/app_db"
+            token_duration_sec = 1800
+        "#;
+
+        // -- Exec
+        let values = parse_config_file(fx_content).map_err(anyhow::Error::msg)?;
+
+        // -- Check
+        assert_eq!(
+            values.get("SERVICE_DB_URL").map(String::as_str),
+            Some("postgres://localhost/app_db")
+        );
+        assert_eq!(
+            values.get("SERVICE_TOKEN_DURATION_SEC").map(String::as_str),
+            Some("1800")
+        );
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
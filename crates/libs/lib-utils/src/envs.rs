@@ -6,6 +6,11 @@ pub fn get_env(name: &'static str) -> Result<String> {
     env::var(name).map_err(|_| Error::MissingEnv(name))
 }
 
+/// Returns `None` when the env is not set (or empty).
+pub fn get_env_opt(name: &'static str) -> Option<String> {
+    env::var(name).ok().filter(|val| !val.is_empty())
+}
+
 pub fn get_env_parse<T: FromStr>(name: &'static str) -> Result<T> {
     let val = get_env(name)?;
     val.parse::<T>().map_err(|_| Error::WrongFormat(name))
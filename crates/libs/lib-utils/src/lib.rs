@@ -1,3 +1,5 @@
 pub mod b64;
+pub mod config;
 pub mod envs;
-pub mod time;
\ No newline at end of file
+pub mod time;
+pub mod validate;
\ No newline at end of file
@@ -0,0 +1,307 @@
+//! Declarative validation for input data types
+//! (e.g., `TaskForCreate`, `TaskForUpdate`).
+//!
+//! Each input type implements `Validate` by listing its field rules
+//! with a `Validator`, which collects all of the field errors
+//! (rather than stopping at the first one).
+//!
+//! ```ignore
+//! impl Validate for TaskForCreate {
+//!     fn validate(&self) -> validate::Result<()> {
+//!         Validator::new()
+//!             .check("title", &self.title, &[StrRule::NotBlank, StrRule::MaxLen(128)])
+//!             .finish()
+//!     }
+//! }
+//! ```
+//!
+//! Note: `Option` values are only checked when present, so that the same rules
+//!       can be used for the `...ForUpdate` types.
+//!
+
+use lazy_regex::Regex;
+use serde::{Deserialize, Serialize};
+
+// region:    --- Validate
+
+pub trait Validate {
+    fn validate(&self) -> Result<()>;
+}
+
+/// Collects the `FieldError`s of the field checks.
+#[derive(Default)]
+pub struct Validator {
+    field_errors: Vec<FieldError>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `value` against all of the `rules`.
+    pub fn check<T>(mut self, field: &str, value: &T, rules: &[T::Rule]) -> Self
+    where
+        T: Checkable + ?Sized,
+    {
+        for rule in rules {
+            if let Some(violation) = value.check(rule) {
+                self.field_errors.push(FieldError {
+                    field: field.to_string(),
+                    violation,
+                });
+            }
+        }
+        self
+    }
+
+    /// Validate a nested `Validate` value, prefixing its field errors
+    /// with `prefix` (e.g., `data.title`).
+    pub fn nested<V>(mut self, prefix: &str, value: &V) -> Self
+    where
+        V: Validate + ?Sized,
+    {
+        if let Err(Error::FieldsInvalid(field_errors)) = value.validate() {
+            self.field_errors
+                .extend(field_errors.into_iter().map(|field_error| FieldError {
+                    field: format!("{prefix}.{}", field_error.field),
+                    ..field_error
+                }));
+        }
+        self
+    }
+
+    /// Add the violations found by the caller (e.g., the pwd policy of lib-auth).
+    pub fn add<I>(mut self, field: &str, violations: I) -> Self
+    where
+        I: IntoIterator<Item = Violation>,
+    {
+        self.field_errors
+            .extend(violations.into_iter().map(|violation| FieldError {
+                field: field.to_string(),
+                violation,
+            }));
+        self
+    }
+
+    pub fn finish(self) -> Result<()> {
+        if self.field_errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::FieldsInvalid(self.field_errors))
+        }
+    }
+}
+
+// endregion: --- Validate
+
+// region:    --- Rules
+
+pub enum StrRule {
+    /// Must not be empty or whitespace only.
+    NotBlank,
+    /// Minimum number of chars.
+    MinLen(usize),
+    /// Maximum number of chars.
+    MaxLen(usize),
+    /// Must match the regex (e.g., `lazy_regex::regex!("^[a-z]+$")`).
+    Pattern(&'static Regex),
+}
+
+pub enum NumRule {
+    /// Inclusive min.
+    Min(i64),
+    /// Inclusive max.
+    Max(i64),
+}
+
+pub trait Checkable {
+    type Rule;
+
+    fn check(&self, rule: &Self::Rule) -> Option<Violation>;
+}
+
+impl Checkable for str {
+    type Rule = StrRule;
+
+    fn check(&self, rule: &StrRule) -> Option<Violation> {
+        match rule {
+            StrRule::NotBlank => self.trim().is_empty().then_some(Violation::Blank),
+            StrRule::MinLen(min) => {
+                let actual = self.chars().count();
+                (actual < *min).then_some(Violation::TooShort { min: *min, actual })
+            }
+            StrRule::MaxLen(max) => {
+                let actual = self.chars().count();
+                (actual > *max).then_some(Violation::TooLong { max: *max, actual })
+            }
+            StrRule::Pattern(regex) => {
+                (!regex.is_match(self)).then(|| Violation::PatternNotMatching {
+                    pattern: regex.as_str().to_string(),
+                })
+            }
+        }
+    }
+}
+
+impl Checkable for String {
+    type Rule = StrRule;
+
+    fn check(&self, rule: &StrRule) -> Option<Violation> {
+        self.as_str().check(rule)
+    }
+}
+
+impl Checkable for i64 {
+    type Rule = NumRule;
+
+    fn check(&self, rule: &NumRule) -> Option<Violation> {
+        match rule {
+            NumRule::Min(min) => (self < min).then_some(Violation::TooSmall {
+                min: *min,
+                actual: *self,
+            }),
+            NumRule::Max(max) => (self > max).then_some(Violation::TooLarge {
+                max: *max,
+                actual: *self,
+            }),
+        }
+    }
+}
+
+impl<T: Checkable> Checkable for Option<T> {
+    type Rule = T::Rule;
+
+    fn check(&self, rule: &T::Rule) -> Option<Violation> {
+        self.as_ref().and_then(|value| value.check(rule))
+    }
+}
+
+// endregion: --- Rules
+
+// region:    --- Field Error
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    #[serde(flatten)]
+    pub violation: Violation,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "rule")]
+pub enum Violation {
+    Blank,
+    TooShort { min: usize, actual: usize },
+    TooLong { max: usize, actual: usize },
+    PatternNotMatching { pattern: String },
+    TooSmall { min: i64, actual: i64 },
+    TooLarge { max: i64, actual: i64 },
+
+    // -- Pwd policy (see `lib_auth::pwd::check_pwd_policy`)
+    /// Fewer character classes (lower, upper, digit, other) than `min`.
+    CharClassesTooFew { min: usize, actual: usize },
+    /// In the common passwords list.
+    TooCommon,
+    /// Same as the value of `field` (e.g., the username).
+    SameAs { field: String },
+}
+
+// endregion: --- Field Error
+
+// region:    --- Error
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, Serialize)]
+pub enum Error {
+    FieldsInvalid(Vec<FieldError>),
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(
+        &self,
+        fmt: &mut core::fmt::Formatter,
+    ) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
+
+// endregion: --- Error
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use lazy_regex::regex;
+
+    struct FxData {
+        name: String,
+        code: Option<String>,
+        count: i64,
+    }
+
+    impl Validate for FxData {
+        fn validate(&self) -> super::Result<()> {
+            Validator::new()
+                .check("name", &self.name, &[StrRule::NotBlank, StrRule::MaxLen(5)])
+                .check("code", &self.code, &[StrRule::Pattern(regex!("^[a-z]+$"))])
+                .check("count", &self.count, &[NumRule::Min(0), NumRule::Max(10)])
+                .finish()
+        }
+    }
+
+    #[test]
+    fn test_validate_ok() -> Result<()> {
+        // -- Fixtures
+        let fx_data = FxData {
+            name: "name".to_string(),
+            code: None,
+            count: 10,
+        };
+
+        // -- Exec & Check
+        fx_data.validate()?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_nested_err_all_fields() -> Result<()> {
+        // -- Fixtures
+        let fx_data = FxData {
+            name: " ".to_string(),
+            code: Some("ABC".to_string()),
+            count: 11,
+        };
+
+        // -- Exec
+        let res = Validator::new().nested("data", &fx_data).finish();
+
+        // -- Check
+        let Err(Error::FieldsInvalid(field_errors)) = res else {
+            panic!(
+                "Should have been `Err(Error::FieldsInvalid(..))` but was `{res:?}`"
+            );
+        };
+        let fields: Vec<&str> =
+            field_errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["data.name", "data.code", "data.count"]);
+        assert!(matches!(field_errors[0].violation, Violation::Blank));
+        assert!(matches!(
+            field_errors[2].violation,
+            Violation::TooLarge {
+                max: 10,
+                actual: 11
+            }
+        ));
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
@@ -1,25 +1,406 @@
-use lib_utils::envs::get_env;
+use crate::rate_limit::{MethodRateLimits, RateLimit};
+use crate::web::AUTH_TOKEN;
+use axum::http::HeaderValue;
+use lib_utils::config::{self, ConfigReader, SettingError};
+use std::net::SocketAddr;
 use std::sync::OnceLock;
+use std::time::Duration;
+use tower_cookies::cookie::SameSite;
+
+const DEFAULT_CSP: &str =
+    "default-src 'self'; frame-ancestors 'none'; base-uri 'self'; form-action 'self'";
+const DEFAULT_PWD_RESET_URL: &str = "http://localhost:8080/pwd-reset?token=";
+const DEFAULT_OIDC_REDIRECT_URL: &str = "http://localhost:8080/api/oidc/callback";
 
 pub fn web_config() -> &'static WebConfig {
     static INSTANCE: OnceLock<WebConfig> = OnceLock::new();
 
     INSTANCE.get_or_init(|| {
-        WebConfig::load_from_env().unwrap_or_else(|ex| {
+        WebConfig::load().unwrap_or_else(|ex| {
             panic!("FATAL - WHILE LOADING CONF - Cause: {ex:?}")
         })
     })
 }
 
+/// Load all of the service configs (core, auth, web), and print all of
+/// their missing or invalid settings (`web-server --check-config`).
+///
+/// Returns `false` when any setting is missing or invalid.
+pub fn check_configs() -> bool {
+    let results = [
+        ("core", lib_core::check_core_config()),
+        ("auth", lib_auth::check_auth_config()),
+        ("web", WebConfig::load().map(|_| ())),
+    ];
+
+    let mut is_ok = true;
+    for (name, result) in results {
+        match result {
+            Ok(()) => println!("{name:<6} - config ok"),
+            Err(config::Error::SettingsInvalid(errors)) => {
+                is_ok = false;
+                for error in errors {
+                    println!("{name:<6} - {error:?}");
+                }
+            }
+        }
+    }
+
+    is_ok
+}
+
 #[allow(non_snake_case)]
 pub struct WebConfig {
+    // -- Server
+    pub LISTEN_ADDR: SocketAddr,
+    /// Serve https when both the cert and key (pem files) are set.
+    pub TLS_CERT_PATH: Option<String>,
+    pub TLS_KEY_PATH: Option<String>,
+    /// Deadline for the in-flight requests to drain on shutdown.
+    pub SHUTDOWN_TIMEOUT_SEC: u64,
+
     pub WEB_FOLDER: String,
+
+    // -- Security
+    /// Origins allowed for the cross-origin requests (comma separated),
+    /// and for the cookie authenticated rpc calls (with the service origin).
+    pub CORS_ALLOWED_ORIGINS: Vec<String>,
+    pub CONTENT_SECURITY_POLICY: Option<HeaderValue>,
+    /// `Strict-Transport-Security` max-age (0 for none).
+    pub HSTS_MAX_AGE_SEC: u64,
+    pub AUTH_COOKIE: CookieConfig,
+    /// Take the client ip from the `X-Forwarded-For` entry appended by the proxy
+    /// (only behind a single trusted proxy).
+    pub TRUST_FORWARDED_FOR: bool,
+
+    // -- Login Guard (see `web::login_guard`)
+    pub LOGIN_MAX_FAILURES: u32,
+    pub LOGIN_IP_MAX_FAILURES: u32,
+    pub LOGIN_LOCKOUT_SEC: u64,
+    pub LOGIN_DELAY_BASE_MS: u64,
+
+    // -- Registration
+    /// Self-service registration at `/api/register`.
+    pub REGISTRATION_ENABLED: bool,
+    /// Link of the pwd reset mails, followed by the reset token
+    /// (e.g., `https://app.example.com/pwd-reset?token=`).
+    pub PWD_RESET_URL: String,
+    /// Min time between two pwd reset mails to a same username.
+    pub PWD_RESET_COOLDOWN_SEC: u64,
+
+    // -- Oidc (see `web::oidc`)
+    /// Single sign-on provider, when `SERVICE_OIDC_ISSUER` is set.
+    pub OIDC: Option<OidcConfig>,
+
+    // -- Mail
+    pub MAIL_SINK: MailSinkConfig,
+    pub MAIL_FROM: String,
+
+    // -- Rate Limit (see `rate_limit`)
+    pub RATE_LIMIT_DEFAULT: RateLimit,
+    pub RATE_LIMIT_METHODS: MethodRateLimits,
+    /// Per client ip limit of each unauthenticated account route
+    /// (e.g., `/api/register`).
+    pub RATE_LIMIT_ACCOUNT: RateLimit,
+
+    // -- Request Log
+    pub LOG_SINK: LogSinkConfig,
+    pub LOG_BATCH_SIZE: usize,
+
+    // -- Tracing
+    /// OTLP (grpc) collector endpoint, spans are only exported when set.
+    pub OTLP_ENDPOINT: Option<String>,
+
+    // -- WebSocket
+    pub WS_PING_INTERVAL_SEC: u64,
+    pub WS_IDLE_TIMEOUT_SEC: u64,
+}
+
+/// Destination of the request log lines (`SERVICE_LOG_SINK`).
+pub enum LogSinkConfig {
+    /// `stdout` - json lines on stdout.
+    Stdout,
+    /// `file` - jsonl file, rotated once it reaches `max_bytes`.
+    File { path: String, max_bytes: u64 },
+    /// `http` - json array batches posted to a collector endpoint.
+    Http { url: String },
+}
+
+/// Destination of the outgoing mails (`SERVICE_MAIL_SINK`).
+pub enum MailSinkConfig {
+    /// `stdout` - the mails as text on stdout (dev).
+    Stdout,
+    /// `file` - the mails appended to a file (dev).
+    File { path: String },
+}
+
+/// OpenID Connect provider (`SERVICE_OIDC_*`), with the service registered as
+/// a confidential client (i.e., with a client secret).
+pub struct OidcConfig {
+    /// As in the provider metadata and id tokens (e.g., `https://sso.example.com`).
+    pub issuer: String,
+    pub client_id: String,
+    pub client_secret: String,
+    /// The `/api/oidc/callback` url, as registered at the provider.
+    pub redirect_url: String,
+    /// Space separated, with `openid` (e.g., `openid email profile`).
+    pub scopes: String,
+}
+
+/// Auth token cookie policy (`SERVICE_COOKIE_*`).
+///
+/// Note: Secure by default, relaxed for the localhost dev in `.cargo/config.toml`.
+pub struct CookieConfig {
+    /// `auth-token`, with the `__Host-` prefix when `SERVICE_COOKIE_HOST_PREFIX`
+    /// (which requires `secure` and no `domain`).
+    pub name: String,
+    pub secure: bool,
+    /// `SERVICE_COOKIE_SAME_SITE` - `strict` | `lax` | `none` (requires `secure`).
+    pub same_site: SameSite,
+    pub domain: Option<String>,
 }
 
 impl WebConfig {
-    fn load_from_env() -> lib_utils::envs::Result<WebConfig> {
-        Ok(WebConfig {
-            WEB_FOLDER: get_env("SERVICE_WEB_FOLDER")?,
-        })
+    fn load() -> config::Result<WebConfig> {
+        let mut reader = ConfigReader::new(&[
+            ("SERVICE_WEB_LISTEN_ADDR", "127.0.0.1:8080"),
+            ("SERVICE_SHUTDOWN_TIMEOUT_SEC", "30"),
+            ("SERVICE_WEB_FOLDER", "web-folder/"),
+            ("SERVICE_CORS_ALLOWED_ORIGINS", ""),
+            ("SERVICE_CONTENT_SECURITY_POLICY", DEFAULT_CSP),
+            ("SERVICE_HSTS_MAX_AGE_SEC", "31536000"),
+            ("SERVICE_COOKIE_SECURE", "true"),
+            ("SERVICE_COOKIE_SAME_SITE", "strict"),
+            ("SERVICE_COOKIE_HOST_PREFIX", "true"),
+            ("SERVICE_TRUST_FORWARDED_FOR", "false"),
+            ("SERVICE_LOGIN_MAX_FAILURES", "5"),
+            ("SERVICE_LOGIN_IP_MAX_FAILURES", "50"),
+            ("SERVICE_LOGIN_LOCKOUT_SEC", "900"),
+            ("SERVICE_LOGIN_DELAY_BASE_MS", "200"),
+            ("SERVICE_REGISTRATION_ENABLED", "false"),
+            ("SERVICE_PWD_RESET_URL", DEFAULT_PWD_RESET_URL),
+            ("SERVICE_PWD_RESET_COOLDOWN_SEC", "300"),
+            ("SERVICE_OIDC_REDIRECT_URL", DEFAULT_OIDC_REDIRECT_URL),
+            ("SERVICE_OIDC_SCOPES", "openid email profile"),
+            ("SERVICE_MAIL_SINK", "stdout"),
+            ("SERVICE_MAIL_FROM", "no-reply@localhost"),
+            ("SERVICE_RATE_LIMIT_DEFAULT", "600/60"),
+            ("SERVICE_RATE_LIMIT_METHODS", ""),
+            ("SERVICE_RATE_LIMIT_ACCOUNT", "10/600"),
+            ("SERVICE_LOG_SINK", "stdout"),
+            ("SERVICE_LOG_BATCH_SIZE", "100"),
+            ("SERVICE_WS_PING_INTERVAL_SEC", "30"),
+            ("SERVICE_WS_IDLE_TIMEOUT_SEC", "300"),
+        ]);
+
+        let tls_cert_path = reader.get_opt("SERVICE_TLS_CERT_PATH");
+        let tls_key_path = reader.get_opt("SERVICE_TLS_KEY_PATH");
+        match (&tls_cert_path, &tls_key_path) {
+            (Some(_), None) => reader.push_error(SettingError::Missing(
+                "SERVICE_TLS_KEY_PATH".to_string(),
+            )),
+            (None, Some(_)) => reader.push_error(SettingError::Missing(
+                "SERVICE_TLS_CERT_PATH".to_string(),
+            )),
+            _ => (),
+        }
+
+        let log_sink = match reader.get::<String>("SERVICE_LOG_SINK").as_str() {
+            "file" => LogSinkConfig::File {
+                path: reader.get("SERVICE_LOG_FILE_PATH"),
+                max_bytes: reader.get("SERVICE_LOG_FILE_MAX_BYTES"),
+            },
+            "http" => LogSinkConfig::Http {
+                url: reader.get("SERVICE_LOG_HTTP_URL"),
+            },
+            "stdout" => LogSinkConfig::Stdout,
+            _ => {
+                reader.push_error(SettingError::WrongFormat(
+                    "SERVICE_LOG_SINK".to_string(),
+                ));
+                LogSinkConfig::Stdout
+            }
+        };
+
+        let mail_sink = match reader.get::<String>("SERVICE_MAIL_SINK").as_str() {
+            "file" => MailSinkConfig::File {
+                path: reader.get("SERVICE_MAIL_FILE_PATH"),
+            },
+            "stdout" => MailSinkConfig::Stdout,
+            _ => {
+                reader.push_error(SettingError::WrongFormat(
+                    "SERVICE_MAIL_SINK".to_string(),
+                ));
+                MailSinkConfig::Stdout
+            }
+        };
+
+        let cors_allowed_origins: Vec<String> = reader
+            .get::<String>("SERVICE_CORS_ALLOWED_ORIGINS")
+            .split(',')
+            .map(str::trim)
+            .filter(|origin| !origin.is_empty())
+            .map(String::from)
+            .collect();
+        let is_origin_invalid = |origin: &String| {
+            !origin.starts_with("http") || HeaderValue::from_str(origin).is_err()
+        };
+        if cors_allowed_origins.iter().any(is_origin_invalid) {
+            reader.push_error(SettingError::WrongFormat(
+                "SERVICE_CORS_ALLOWED_ORIGINS".to_string(),
+            ));
+        }
+
+        // Note: 0 would make the ws ping interval panic (on each connection).
+        let ws_ping_interval_sec: Option<u64> =
+            reader.get_opt("SERVICE_WS_PING_INTERVAL_SEC");
+        if ws_ping_interval_sec == Some(0) {
+            reader.push_error(SettingError::WrongFormat(
+                "SERVICE_WS_PING_INTERVAL_SEC".to_string(),
+            ));
+        }
+
+        let auth_cookie = load_cookie_config(&mut reader);
+        let oidc = load_oidc_config(&mut reader);
+
+        let config = WebConfig {
+            // -- Server
+            // Note: Always set (defaults), unless invalid (then reported by `finish`).
+            LISTEN_ADDR: reader
+                .get_opt("SERVICE_WEB_LISTEN_ADDR")
+                .unwrap_or_else(|| SocketAddr::from(([127, 0, 0, 1], 8080))),
+            TLS_CERT_PATH: tls_cert_path,
+            TLS_KEY_PATH: tls_key_path,
+            SHUTDOWN_TIMEOUT_SEC: reader.get("SERVICE_SHUTDOWN_TIMEOUT_SEC"),
+
+            WEB_FOLDER: reader.get("SERVICE_WEB_FOLDER"),
+
+            // -- Security
+            CORS_ALLOWED_ORIGINS: cors_allowed_origins,
+            CONTENT_SECURITY_POLICY: reader
+                .get_opt("SERVICE_CONTENT_SECURITY_POLICY"),
+            HSTS_MAX_AGE_SEC: reader.get("SERVICE_HSTS_MAX_AGE_SEC"),
+            AUTH_COOKIE: auth_cookie,
+            TRUST_FORWARDED_FOR: reader.get("SERVICE_TRUST_FORWARDED_FOR"),
+
+            // -- Login Guard
+            LOGIN_MAX_FAILURES: reader.get("SERVICE_LOGIN_MAX_FAILURES"),
+            LOGIN_IP_MAX_FAILURES: reader.get("SERVICE_LOGIN_IP_MAX_FAILURES"),
+            LOGIN_LOCKOUT_SEC: reader.get("SERVICE_LOGIN_LOCKOUT_SEC"),
+            LOGIN_DELAY_BASE_MS: reader.get("SERVICE_LOGIN_DELAY_BASE_MS"),
+
+            // -- Registration
+            REGISTRATION_ENABLED: reader.get("SERVICE_REGISTRATION_ENABLED"),
+            PWD_RESET_URL: reader.get("SERVICE_PWD_RESET_URL"),
+            PWD_RESET_COOLDOWN_SEC: reader.get("SERVICE_PWD_RESET_COOLDOWN_SEC"),
+
+            // -- Oidc
+            OIDC: oidc,
+
+            // -- Mail
+            MAIL_SINK: mail_sink,
+            MAIL_FROM: reader.get("SERVICE_MAIL_FROM"),
+
+            // -- Rate Limit
+            // Note: Always set (defaults), unless invalid (then reported by `finish`).
+            RATE_LIMIT_DEFAULT: reader
+                .get_opt("SERVICE_RATE_LIMIT_DEFAULT")
+                .unwrap_or(RateLimit {
+                    requests: 600,
+                    period: Duration::from_secs(60),
+                }),
+            RATE_LIMIT_METHODS: reader.get("SERVICE_RATE_LIMIT_METHODS"),
+            RATE_LIMIT_ACCOUNT: reader
+                .get_opt("SERVICE_RATE_LIMIT_ACCOUNT")
+                .unwrap_or(RateLimit {
+                    requests: 10,
+                    period: Duration::from_secs(600),
+                }),
+
+            // -- Request Log
+            LOG_SINK: log_sink,
+            LOG_BATCH_SIZE: reader.get("SERVICE_LOG_BATCH_SIZE"),
+
+            // -- Tracing
+            OTLP_ENDPOINT: reader.get_opt("SERVICE_OTLP_ENDPOINT"),
+
+            // -- WebSocket
+            // Note: Always set (default), unless invalid (then reported by `finish`).
+            WS_PING_INTERVAL_SEC: ws_ping_interval_sec.unwrap_or_default(),
+            WS_IDLE_TIMEOUT_SEC: reader.get("SERVICE_WS_IDLE_TIMEOUT_SEC"),
+        };
+
+        reader.finish()?;
+        Ok(config)
+    }
+}
+
+/// Note: `None` when `SERVICE_OIDC_ISSUER` is not set, the other settings
+///       being then ignored.
+fn load_oidc_config(reader: &mut ConfigReader) -> Option<OidcConfig> {
+    let issuer: String = reader.get_opt("SERVICE_OIDC_ISSUER")?;
+
+    if !issuer.starts_with("http") {
+        reader.push_error(SettingError::WrongFormat(
+            "SERVICE_OIDC_ISSUER".to_string(),
+        ));
+    }
+
+    let scopes: String = reader.get("SERVICE_OIDC_SCOPES");
+    if !scopes.split_whitespace().any(|scope| scope == "openid") {
+        reader.push_error(SettingError::WrongFormat(
+            "SERVICE_OIDC_SCOPES".to_string(),
+        ));
+    }
+
+    Some(OidcConfig {
+        issuer,
+        client_id: reader.get("SERVICE_OIDC_CLIENT_ID"),
+        client_secret: reader.get("SERVICE_OIDC_CLIENT_SECRET"),
+        redirect_url: reader.get("SERVICE_OIDC_REDIRECT_URL"),
+        scopes,
+    })
+}
+
+fn load_cookie_config(reader: &mut ConfigReader) -> CookieConfig {
+    let secure: bool = reader.get("SERVICE_COOKIE_SECURE");
+    let host_prefix: bool = reader.get("SERVICE_COOKIE_HOST_PREFIX");
+    let domain: Option<String> = reader.get_opt("SERVICE_COOKIE_DOMAIN");
+
+    let same_site = match reader
+        .get::<String>("SERVICE_COOKIE_SAME_SITE")
+        .to_lowercase()
+        .as_str()
+    {
+        "strict" => SameSite::Strict,
+        "lax" => SameSite::Lax,
+        "none" if secure => SameSite::None,
+        _ => {
+            reader.push_error(SettingError::WrongFormat(
+                "SERVICE_COOKIE_SAME_SITE".to_string(),
+            ));
+            SameSite::Strict
+        }
+    };
+
+    // `__Host-` cookies are rejected by the browsers unless secure,
+    // with `Path=/` and without domain.
+    if host_prefix && (!secure || domain.is_some()) {
+        reader.push_error(SettingError::WrongFormat(
+            "SERVICE_COOKIE_HOST_PREFIX".to_string(),
+        ));
+    }
+
+    let name = if host_prefix {
+        format!("__Host-{AUTH_TOKEN}")
+    } else {
+        AUTH_TOKEN.to_string()
+    };
+
+    CookieConfig {
+        name,
+        secure,
+        same_site,
+        domain,
     }
 }
@@ -8,6 +8,16 @@ pub enum Error {
     // -- Modules
     #[from]
     Model(model::Error),
+
+    // -- Externals
+    #[from]
+    Io(std::io::Error), // e.g., bind, tls pem files, or log file.
+    #[from]
+    SerdeJson(serde_json::Error),
+    #[from]
+    Reqwest(reqwest::Error), // e.g., http log sink.
+    #[from]
+    OtelTrace(opentelemetry::trace::TraceError),
 }
 
 // region:    --- Error Boilerplate
@@ -1,29 +1,51 @@
+//! Request log.
+//!
+//! - `log_request` builds the `RequestLogLine` and queues it, without waiting.
+//! - A background task writes the queued lines to the configured `LogSink`,
+//!   in batches of `LOG_BATCH_SIZE` lines (or every `FLUSH_INTERVAL`).
+//! - When the queue is full (e.g., slow sink), the line is dropped rather
+//!   than delaying the response.
+
+// region:    --- Modules
+
+mod sinks;
+
+pub use self::sinks::{FileSink, HttpBatchSink, LogSink, StdoutSink};
+
+use crate::config::LogSinkConfig;
 use crate::web::routes_rpc::RpcInfo;
 use crate::web::{self, ClientError};
+use crate::web_config;
 use crate::Result;
 use axum::http::{Method, Uri};
 use lib_core::ctx::Ctx;
+use lib_utils::time::{format_time, now_utc};
 use serde::Serialize;
 use serde_json::{json, Value};
 use serde_with::skip_serializing_none;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::debug;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, warn};
 use uuid::Uuid;
 
+// endregion: --- Modules
+
+const QUEUE_CAPACITY: usize = 4096;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[allow(clippy::too_many_arguments)]
 pub async fn log_request(
     uuid: Uuid,
     req_method: Method,
     uri: Uri,
+    latency: Duration,
     rpc_info: Option<&RpcInfo>,
     ctx: Option<Ctx>,
     web_error: Option<&web::Error>,
     client_error: Option<ClientError>,
 ) -> Result<()> {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_millis();
-
     let error_type = web_error.map(|se| se.as_ref().to_string());
     let error_data = serde_json::to_value(web_error)
         .ok()
@@ -32,7 +54,8 @@ pub async fn log_request(
     // Create the RequestLogLine
     let log_line = RequestLogLine {
         uuid: uuid.to_string(),
-        timestamp: timestamp.to_string(),
+        timestamp: format_time(now_utc()),
+        latency_ms: latency.as_secs_f64() * 1000.,
 
         http_path: uri.to_string(),
         http_method: req_method.to_string(),
@@ -40,7 +63,8 @@ pub async fn log_request(
         rpc_id: rpc_info.and_then(|rpc| rpc.id.as_ref().map(|id| id.to_string())),
         rpc_method: rpc_info.map(|rpc| rpc.method.to_string()),
 
-        user_id: ctx.map(|c| c.user_id()),
+        user_id: ctx.as_ref().map(|c| c.user_id()),
+        impersonator_id: ctx.and_then(|c| c.impersonator_id()),
 
         client_error_type: client_error.map(|e| e.as_ref().to_string()),
 
@@ -50,19 +74,21 @@ pub async fn log_request(
 
     debug!("REQUEST LOG LINE:\n{}", json!(log_line));
 
-    // TODO - Send to cloud-watch.
+    queue_line(log_line);
 
     Ok(())
 }
 
 #[skip_serializing_none]
 #[derive(Serialize)]
-struct RequestLogLine {
+pub struct RequestLogLine {
     uuid: String,      // uuid string formatted
-    timestamp: String, // (should be iso8601)
+    timestamp: String, // Rfc3339 (iso8601)
+    latency_ms: f64,
 
     // -- User and context attributes.
     user_id: Option<i64>,
+    impersonator_id: Option<i64>, // The admin acting as the user, if any.
 
     // -- http request attributes.
     http_path: String,
@@ -77,3 +103,107 @@ struct RequestLogLine {
     error_type: Option<String>,
     error_data: Option<Value>,
 }
+
+// region:    --- Log Sink Queue
+
+enum LogMsg {
+    Line(Box<RequestLogLine>),
+    Flush(oneshot::Sender<()>),
+}
+
+static LOG_TX: OnceLock<mpsc::Sender<LogMsg>> = OnceLock::new();
+
+/// Start the background task writing the request log lines
+/// to the `LOG_SINK` of the web config.
+pub fn init_log_sink() {
+    let config = web_config();
+    let sink: Box<dyn LogSink> = match &config.LOG_SINK {
+        LogSinkConfig::Stdout => Box::new(StdoutSink),
+        LogSinkConfig::File { path, max_bytes } => {
+            Box::new(FileSink::new(path, *max_bytes))
+        }
+        LogSinkConfig::Http { url } => Box::new(HttpBatchSink::new(url)),
+    };
+
+    let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+    if LOG_TX.set(tx).is_ok() {
+        tokio::spawn(run_log_sink(sink, config.LOG_BATCH_SIZE, rx));
+    }
+}
+
+/// Wait for the queued lines to be written (e.g., on shutdown).
+pub async fn flush_log_sink() {
+    let Some(tx) = LOG_TX.get() else {
+        return;
+    };
+
+    let (done_tx, done_rx) = oneshot::channel();
+    if tx.send(LogMsg::Flush(done_tx)).await.is_ok() {
+        let _ = done_rx.await;
+    }
+}
+
+fn queue_line(log_line: RequestLogLine) {
+    let Some(tx) = LOG_TX.get() else {
+        return;
+    };
+
+    match tx.try_send(LogMsg::Line(Box::new(log_line))) {
+        Ok(()) => (),
+        Err(TrySendError::Full(_)) => warn!("{:<12} - queue full, line dropped", "LOG_SINK"),
+        Err(TrySendError::Closed(_)) => (),
+    }
+}
+
+async fn run_log_sink(
+    mut sink: Box<dyn LogSink>,
+    batch_size: usize,
+    mut rx: mpsc::Receiver<LogMsg>,
+) {
+    let mut batch: Vec<RequestLogLine> = Vec::with_capacity(batch_size);
+    let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+
+    loop {
+        tokio::select! {
+            msg = rx.recv() => match msg {
+                Some(LogMsg::Line(line)) => {
+                    batch.push(*line);
+                    if batch.len() >= batch_size {
+                        write_batch(sink.as_mut(), &mut batch).await;
+                    }
+                }
+                Some(LogMsg::Flush(done_tx)) => {
+                    write_batch(sink.as_mut(), &mut batch).await;
+                    let _ = done_tx.send(());
+                }
+                None => {
+                    write_batch(sink.as_mut(), &mut batch).await;
+                    break;
+                }
+            },
+
+            _ = flush_interval.tick() => write_batch(sink.as_mut(), &mut batch).await,
+        }
+    }
+}
+
+/// Write and clear the batch.
+///
+/// Note: A failing batch is dropped (after a warning), so that a down sink
+///       does not grow the memory.
+async fn write_batch(sink: &mut dyn LogSink, batch: &mut Vec<RequestLogLine>) {
+    if batch.is_empty() {
+        return;
+    }
+
+    if let Err(ex) = sink.write_batch(batch).await {
+        warn!(
+            "{:<12} - write fail, {} lines dropped - {ex:?}",
+            "LOG_SINK",
+            batch.len()
+        );
+    }
+    batch.clear();
+}
+
+// endregion: --- Log Sink Queue
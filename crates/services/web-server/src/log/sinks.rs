@@ -0,0 +1,162 @@
+//! The `LogSink` implementations (stdout json, rotating jsonl file, http batch).
+
+use crate::log::RequestLogLine;
+use crate::Result;
+use async_trait::async_trait;
+use lib_utils::time::{format_time, now_utc};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::fs::{self, File, OpenOptions};
+use tokio::io::{self, AsyncWriteExt};
+
+/// Destination of the request log lines.
+///
+/// Note: Called by a single background task, with the lines in batches
+///       (see `log::init_log_sink`), so implementations can be slow.
+#[async_trait]
+pub trait LogSink: Send {
+    async fn write_batch(&mut self, lines: &[RequestLogLine]) -> Result<()>;
+}
+
+/// Serialize the lines as json lines (one json object per line).
+fn to_jsonl(lines: &[RequestLogLine]) -> Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    for line in lines {
+        serde_json::to_writer(&mut buf, line)?;
+        buf.push(b'\n');
+    }
+
+    Ok(buf)
+}
+
+// region:    --- StdoutSink
+
+pub struct StdoutSink;
+
+#[async_trait]
+impl LogSink for StdoutSink {
+    async fn write_batch(&mut self, lines: &[RequestLogLine]) -> Result<()> {
+        let mut stdout = io::stdout();
+        stdout.write_all(&to_jsonl(lines)?).await?;
+        stdout.flush().await?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- StdoutSink
+
+// region:    --- FileSink
+
+/// Append the lines to a jsonl file, which is rotated (renamed with a
+/// timestamp suffix, e.g., `request.log.2023-09-01T10:00:00Z`) once it
+/// reaches `max_bytes`.
+pub struct FileSink {
+    path: PathBuf,
+    max_bytes: u64,
+    file: Option<(File, u64)>, // (file, current size)
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>, max_bytes: u64) -> Self {
+        FileSink {
+            path: path.into(),
+            max_bytes,
+            file: None,
+        }
+    }
+
+    async fn open(&self) -> Result<(File, u64)> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        let size = file.metadata().await?.len();
+
+        Ok((file, size))
+    }
+
+    async fn rotate(&mut self) -> Result<()> {
+        self.file = None;
+
+        let mut rotated_path = self.path.clone().into_os_string();
+        rotated_path.push(format!(".{}", format_time(now_utc())));
+        fs::rename(&self.path, rotated_path).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl LogSink for FileSink {
+    async fn write_batch(&mut self, lines: &[RequestLogLine]) -> Result<()> {
+        let buf = to_jsonl(lines)?;
+
+        if self.file.is_none() {
+            self.file = Some(self.open().await?);
+        }
+        if let Some((_, size)) = &self.file {
+            if *size > 0 && size + buf.len() as u64 > self.max_bytes {
+                self.rotate().await?;
+                self.file = Some(self.open().await?);
+            }
+        }
+
+        if let Some((file, size)) = &mut self.file {
+            file.write_all(&buf).await?;
+            file.flush().await?;
+            *size += buf.len() as u64;
+        }
+
+        Ok(())
+    }
+}
+
+// endregion: --- FileSink
+
+// region:    --- HttpBatchSink
+
+/// Deadline of a batch post, after which the batch fails (and is dropped).
+///
+/// Note: A hanging collector would otherwise block the sink task, and the
+///       queue would drop all of the next lines.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Post each batch as a json array to a collector endpoint.
+pub struct HttpBatchSink {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl HttpBatchSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .unwrap_or_else(|ex| {
+                panic!("FATAL - WHILE BUILDING LOG SINK HTTP CLIENT - Cause: {ex:?}")
+            });
+
+        HttpBatchSink {
+            client,
+            url: url.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl LogSink for HttpBatchSink {
+    async fn write_batch(&mut self, lines: &[RequestLogLine]) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(lines)
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- HttpBatchSink
@@ -0,0 +1,67 @@
+//! Outgoing mails (e.g., the pwd reset mails).
+//!
+//! - The mails are sent by the `MAIL_SINK` `MailSender`, for now the dev ones
+//!   (stdout, or a file), a smtp (or mail api) sender being another
+//!   `MailSender` implementation.
+//! - The mails are sent in the background, so that the requests do not wait
+//!   for (and their response time does not depend on) the delivery.
+
+// region:    --- Modules
+
+mod senders;
+
+pub use self::senders::{FileSender, MailSender, StdoutSender};
+
+use crate::config::MailSinkConfig;
+use crate::web_config;
+use serde::Serialize;
+use std::sync::OnceLock;
+use tracing::warn;
+
+// endregion: --- Modules
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Mail {
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+impl Mail {
+    /// New mail from the `MAIL_FROM` address.
+    pub fn new(
+        to: impl Into<String>,
+        subject: impl Into<String>,
+        body: impl Into<String>,
+    ) -> Self {
+        Mail {
+            from: web_config().MAIL_FROM.clone(),
+            to: to.into(),
+            subject: subject.into(),
+            body: body.into(),
+        }
+    }
+}
+
+fn mail_sender() -> &'static dyn MailSender {
+    static INSTANCE: OnceLock<Box<dyn MailSender>> = OnceLock::new();
+
+    INSTANCE
+        .get_or_init(|| match &web_config().MAIL_SINK {
+            MailSinkConfig::Stdout => Box::new(StdoutSender),
+            MailSinkConfig::File { path } => Box::new(FileSender::new(path)),
+        })
+        .as_ref()
+}
+
+/// Send the mail in the background.
+///
+/// Note: A failing mail is dropped (after a warning).
+pub fn send_mail(mail: Mail) {
+    tokio::spawn(async move {
+        if let Err(ex) = mail_sender().send(&mail).await {
+            warn!("{:<12} - send fail, to '{}' - {ex:?}", "MAIL", mail.to);
+        }
+    });
+}
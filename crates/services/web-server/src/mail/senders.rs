@@ -0,0 +1,74 @@
+//! The `MailSender` implementations (stdout, file).
+
+use crate::mail::Mail;
+use crate::Result;
+use async_trait::async_trait;
+use lib_utils::time::{format_time, now_utc};
+use std::path::PathBuf;
+use tokio::fs::OpenOptions;
+use tokio::io::{self, AsyncWriteExt};
+
+#[async_trait]
+pub trait MailSender: Send + Sync {
+    async fn send(&self, mail: &Mail) -> Result<()>;
+}
+
+/// The mail as text, in a mbox like format (for the dev senders).
+fn to_text(mail: &Mail) -> String {
+    format!(
+        "From {} {}\nFrom: {}\nTo: {}\nSubject: {}\n\n{}\n\n",
+        mail.from,
+        format_time(now_utc()),
+        mail.from,
+        mail.to,
+        mail.subject,
+        mail.body
+    )
+}
+
+// region:    --- StdoutSender
+
+pub struct StdoutSender;
+
+#[async_trait]
+impl MailSender for StdoutSender {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        let mut stdout = io::stdout();
+        stdout.write_all(to_text(mail).as_bytes()).await?;
+        stdout.flush().await?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- StdoutSender
+
+// region:    --- FileSender
+
+/// Append the mails to a file (e.g., `mail.mbox`).
+pub struct FileSender {
+    path: PathBuf,
+}
+
+impl FileSender {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        FileSender { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl MailSender for FileSender {
+    async fn send(&self, mail: &Mail) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(to_text(mail).as_bytes()).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- FileSender
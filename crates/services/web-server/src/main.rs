@@ -5,58 +5,155 @@
 mod config;
 mod error;
 mod log;
+mod mail;
+mod metrics;
+mod rate_limit;
+mod telemetry;
 mod web;
 
 pub use self::error::{Error, Result};
 use config::web_config;
 
 use crate::web::mw_auth::{mw_ctx_require, mw_ctx_resolve};
+use crate::web::mw_csrf::mw_csrf_check;
+use crate::web::mw_metrics::mw_metrics;
+use crate::web::mw_rate_limit::{mw_rate_limit, mw_rate_limit_ip};
+use crate::web::mw_req_stamp::mw_req_stamp;
 use crate::web::mw_res_map::mw_reponse_map;
-use crate::web::{routes_login, routes_rpc, routes_static};
+use crate::web::mw_security::{cors_layer, mw_security_headers};
+use crate::web::{
+	routes_health, routes_login, routes_metrics, routes_oidc, routes_pwd_reset,
+	routes_register, routes_rpc, routes_sse, routes_static, routes_ws,
+};
 use axum::{middleware, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use axum_server::Handle;
 use lib_core::_dev_utils;
-use lib_core::model::ModelManager;
+use lib_core::model::{set_acquire_wait_observer, ModelManager};
 use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::signal;
 use tower_cookies::CookieManagerLayer;
 use tracing::info;
-use tracing_subscriber::EnvFilter;
 
 // endregion: --- Modules
 
 #[tokio::main]
 async fn main() -> Result<()> {
-	tracing_subscriber::fmt()
-		.without_time() // For early local development.
-		.with_target(false)
-		.with_env_filter(EnvFilter::from_default_env())
-		.init();
+	// -- Check the configs only (e.g., before a deploy).
+	if std::env::args().any(|arg| arg == "--check-config") {
+		let is_ok = config::check_configs();
+		std::process::exit(if is_ok { 0 } else { 1 });
+	}
+
+	telemetry::init_tracing()?;
 
 	// -- FOR DEV ONLY
 	_dev_utils::init_dev().await;
 
+	// Start the request log sink.
+	log::init_log_sink();
+
 	// Initialize ModelManager.
+	set_acquire_wait_observer(metrics::observe_db_acquire_wait);
 	let mm = ModelManager::new().await?;
 
 	// -- Define Routes
 	let routes_rpc = routes_rpc::routes(mm.clone())
-		.route_layer(middleware::from_fn(mw_ctx_require));
+		.merge(routes_ws::routes(mm.clone()))
+		.merge(routes_sse::routes(mm.clone()))
+		.route_layer(middleware::from_fn(mw_ctx_require))
+		.route_layer(middleware::from_fn(mw_csrf_check))
+		.route_layer(middleware::from_fn(mw_rate_limit))
+		.route_layer(middleware::from_fn(mw_metrics));
+
+	// Note: Not authenticated, so rate limited per client ip
+	//       (the login has its own guard, see `web::login_guard`).
+	let routes_account = routes_register::routes(mm.clone())
+		.merge(routes_pwd_reset::routes(mm.clone()))
+		.route_layer(middleware::from_fn(mw_rate_limit_ip));
+
+	let routes_login = routes_login::routes(mm.clone())
+		.merge(routes_account)
+		.merge(routes_oidc::routes(mm.clone()))
+		.route_layer(middleware::from_fn(mw_metrics));
 
 	let routes_all = Router::new()
-		.merge(routes_login::routes(mm.clone()))
+		.merge(routes_login)
 		.nest("/api", routes_rpc)
 		.layer(middleware::map_response(mw_reponse_map))
 		.layer(middleware::from_fn_with_state(mm.clone(), mw_ctx_resolve))
 		.layer(CookieManagerLayer::new())
-		.fallback_service(routes_static::serve_dir());
+		.layer(middleware::from_fn(mw_req_stamp))
+		// Note: Merged after the layers, so that the probes and metrics bypass
+		//       the ctx resolve and the request log.
+		.merge(routes_health::routes(mm.clone()))
+		.merge(routes_metrics::routes(mm.clone()))
+		.fallback_service(routes_static::serve_dir())
+		// Note: Layered last, so that the static files get them as well.
+		.layer(middleware::from_fn(mw_security_headers))
+		.layer(cors_layer());
 
 	// region:    --- Start Server
-	let addr = SocketAddr::from(([127, 0, 0, 1], 8080));
-	info!("{:<12} - {addr}\n", "LISTENING");
-	axum::Server::bind(&addr)
-		.serve(routes_all.into_make_service())
-		.await
-		.unwrap();
+	let config = web_config();
+	let handle = Handle::new();
+	tokio::spawn(shutdown_on_signal(
+		handle.clone(),
+		Duration::from_secs(config.SHUTDOWN_TIMEOUT_SEC),
+	));
+
+	let addr = config.LISTEN_ADDR;
+	// Note: With the connect info, for the client ip (see `web::client_ip`).
+	let app = routes_all.into_make_service_with_connect_info::<SocketAddr>();
+	match (&config.TLS_CERT_PATH, &config.TLS_KEY_PATH) {
+		(Some(cert_path), Some(key_path)) => {
+			let tls_config = RustlsConfig::from_pem_file(cert_path, key_path).await?;
+			info!("{:<12} - https://{addr}\n", "LISTENING");
+			axum_server::bind_rustls(addr, tls_config)
+				.handle(handle)
+				.serve(app)
+				.await?;
+		}
+		_ => {
+			info!("{:<12} - http://{addr}\n", "LISTENING");
+			axum_server::bind(addr).handle(handle).serve(app).await?;
+		}
+	}
 	// endregion: --- Start Server
 
+	// -- Close the db pool once the requests are drained.
+	mm.close().await;
+	log::flush_log_sink().await;
+	telemetry::shutdown_tracing();
+	info!("{:<12} - done", "SHUTDOWN");
+
 	Ok(())
 }
+
+/// Wait for SIGINT/SIGTERM, then stop accepting connections and let the
+/// in-flight requests drain until `timeout` (after which they are dropped).
+async fn shutdown_on_signal(handle: Handle, timeout: Duration) {
+	let ctrl_c = async {
+		let _ = signal::ctrl_c().await;
+	};
+
+	#[cfg(unix)]
+	let terminate = async {
+		match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+			Ok(mut sigterm) => {
+				sigterm.recv().await;
+			}
+			Err(_) => std::future::pending::<()>().await,
+		}
+	};
+	#[cfg(not(unix))]
+	let terminate = std::future::pending::<()>();
+
+	tokio::select! {
+		_ = ctrl_c => (),
+		_ = terminate => (),
+	}
+
+	info!("{:<12} - draining requests (timeout: {timeout:?})", "SHUTDOWN");
+	handle.graceful_shutdown(Some(timeout));
+}
@@ -0,0 +1,175 @@
+//! Prometheus metrics, exposed by `GET /metrics` (see `web::routes_metrics`).
+//!
+//! - http requests count and latency, per route (matched path) and status.
+//! - rpc calls count and latency, per rpc method (http and websocket).
+//! - errors count, per `ClientError` variant and `web::Error` type.
+//! - login attempts count, per result.
+//! - db pool size/idle/max, read at scrape time.
+//! - db pool acquire wait, per acquire of the Bmc queries
+//!   (see `lib_core::model::set_acquire_wait_observer`).
+
+use crate::web::{self, ClientError};
+use lib_core::model::ModelManager;
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter_vec,
+    register_int_gauge_vec, Encoder, Histogram, HistogramVec, IntCounterVec,
+    IntGaugeVec, TextEncoder,
+};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// region:    --- Registry
+
+struct Metrics {
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    rpc_calls: IntCounterVec,
+    rpc_duration: HistogramVec,
+    errors: IntCounterVec,
+    logins: IntCounterVec,
+    db_pool: IntGaugeVec,
+    db_pool_acquire_wait: Histogram,
+}
+
+fn metrics() -> &'static Metrics {
+    static INSTANCE: OnceLock<Metrics> = OnceLock::new();
+
+    INSTANCE.get_or_init(|| {
+        Metrics::register().unwrap_or_else(|ex| {
+            panic!("FATAL - WHILE REGISTERING METRICS - Cause: {ex:?}")
+        })
+    })
+}
+
+impl Metrics {
+    fn register() -> prometheus::Result<Metrics> {
+        Ok(Metrics {
+            http_requests: register_int_counter_vec!(
+                "http_requests_total",
+                "Number of http requests.",
+                &["method", "route", "status"]
+            )?,
+            http_duration: register_histogram_vec!(
+                "http_request_duration_seconds",
+                "Http request latency.",
+                &["method", "route"]
+            )?,
+            rpc_calls: register_int_counter_vec!(
+                "rpc_calls_total",
+                "Number of rpc calls.",
+                &["rpc_method", "result"]
+            )?,
+            rpc_duration: register_histogram_vec!(
+                "rpc_call_duration_seconds",
+                "Rpc call latency.",
+                &["rpc_method"]
+            )?,
+            errors: register_int_counter_vec!(
+                "errors_total",
+                "Number of request errors.",
+                &["client_error", "error_type"]
+            )?,
+            logins: register_int_counter_vec!(
+                "logins_total",
+                "Number of login attempts.",
+                &["result"]
+            )?,
+            db_pool: register_int_gauge_vec!(
+                "db_pool_connections",
+                "Db pool connections (max, size, idle).",
+                &["state"]
+            )?,
+            db_pool_acquire_wait: register_histogram!(
+                "db_pool_acquire_wait_seconds",
+                "Db pool connection acquire wait."
+            )?,
+        })
+    }
+}
+
+// endregion: --- Registry
+
+// region:    --- Recorders
+
+/// Note: `route` should be the matched path (e.g., `/api/rpc`) rather than
+///       the uri, to keep the label cardinality bounded.
+pub fn observe_http(method: &str, route: &str, status: u16, duration: Duration) {
+    let metrics = metrics();
+    metrics
+        .http_requests
+        .with_label_values(&[method, route, &status.to_string()])
+        .inc();
+    metrics
+        .http_duration
+        .with_label_values(&[method, route])
+        .observe(duration.as_secs_f64());
+}
+
+/// Note: The `rpc_method` comes from the client, so the methods not served
+///       by `lib_rpc::exec_rpc` are recorded as `unknown`, to keep the label
+///       cardinality bounded.
+pub fn observe_rpc(rpc_method: &str, is_ok: bool, duration: Duration) {
+    let metrics = metrics();
+    let rpc_method = if lib_rpc::RPC_METHODS.contains(&rpc_method) {
+        rpc_method
+    } else {
+        "unknown"
+    };
+    let result = if is_ok { "ok" } else { "error" };
+    metrics
+        .rpc_calls
+        .with_label_values(&[rpc_method, result])
+        .inc();
+    metrics
+        .rpc_duration
+        .with_label_values(&[rpc_method])
+        .observe(duration.as_secs_f64());
+}
+
+pub fn observe_error(web_error: &web::Error, client_error: &ClientError) {
+    metrics()
+        .errors
+        .with_label_values(&[client_error.as_ref(), web_error.as_ref()])
+        .inc();
+}
+
+pub fn observe_login(is_ok: bool) {
+    let result = if is_ok { "success" } else { "failure" };
+    metrics().logins.with_label_values(&[result]).inc();
+}
+
+pub fn observe_db_acquire_wait(wait: Duration) {
+    metrics().db_pool_acquire_wait.observe(wait.as_secs_f64());
+}
+
+// endregion: --- Recorders
+
+// region:    --- Export
+
+/// Gather the metrics in the prometheus text format,
+/// after refreshing the db pool gauges.
+pub fn export(mm: &ModelManager) -> String {
+    let metrics = metrics();
+
+    let pool = mm.db_pool_stats();
+    metrics
+        .db_pool
+        .with_label_values(&["max"])
+        .set(pool.max as i64);
+    metrics
+        .db_pool
+        .with_label_values(&["size"])
+        .set(pool.size as i64);
+    metrics
+        .db_pool
+        .with_label_values(&["idle"])
+        .set(pool.idle as i64);
+
+    let mut buffer = Vec::new();
+    // Note: Text encoding of the gathered metrics cannot fail.
+    let _ = TextEncoder::new().encode(&prometheus::gather(), &mut buffer);
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+// endregion: --- Export
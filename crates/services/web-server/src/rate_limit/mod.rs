@@ -0,0 +1,218 @@
+//! Api rate limiting (token buckets).
+//!
+//! - The callers are keyed by their user id when authenticated,
+//!   and by their client ip otherwise.
+//! - The rpc methods of `RATE_LIMIT_METHODS` have their own bucket (and limit),
+//!   the other requests share the `RATE_LIMIT_DEFAULT` one.
+//! - The unauthenticated account routes (e.g., `/api/register`) are keyed by
+//!   client ip only, with a bucket per route (`RATE_LIMIT_ACCOUNT`).
+//! - The buckets are kept by a `RateLimitStore` (in memory for now).
+//!
+//! Applied to the http requests by `web::mw_rate_limit` (`web::mw_rate_limit_ip`
+//! for the account routes), and to the websocket rpc calls by `web::routes_ws`.
+
+// region:    --- Modules
+
+mod stores;
+
+pub use self::stores::{MemoryStore, RateLimitStore};
+
+use crate::web_config;
+use std::net::IpAddr;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tracing::warn;
+
+// endregion: --- Modules
+
+// region:    --- RateLimit
+
+/// Bucket limit, `{requests}/{period_sec}` (e.g., `100/60`), allowing bursts
+/// of `requests`, refilled at `requests / period_sec` per second.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn refill_per_sec(&self) -> f64 {
+        self.requests as f64 / self.period.as_secs_f64()
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        let (requests, period_sec) = s.trim().split_once('/').ok_or_else(|| {
+            format!("'{s}' should be '{{requests}}/{{period_sec}}'")
+        })?;
+        let requests: u32 = requests.trim().parse().map_err(|_| s.to_string())?;
+        let period_sec: u64 =
+            period_sec.trim().parse().map_err(|_| s.to_string())?;
+        if requests == 0 || period_sec == 0 {
+            return Err(format!("'{s}' should not be zero"));
+        }
+
+        Ok(RateLimit {
+            requests,
+            period: Duration::from_secs(period_sec),
+        })
+    }
+}
+
+/// Per rpc method limits, `{rpc_method}={limit},...`
+/// (e.g., `create_task=10/60,list_tasks=300/60`).
+#[derive(Debug, Clone, Default)]
+pub struct MethodRateLimits(pub Vec<(String, RateLimit)>);
+
+impl MethodRateLimits {
+    pub fn get(&self, rpc_method: &str) -> Option<&RateLimit> {
+        self.0
+            .iter()
+            .find(|(method, _)| method == rpc_method)
+            .map(|(_, limit)| limit)
+    }
+}
+
+impl FromStr for MethodRateLimits {
+    type Err = String;
+
+    fn from_str(s: &str) -> core::result::Result<Self, Self::Err> {
+        s.split(',')
+            .filter(|entry| !entry.trim().is_empty())
+            .map(|entry| {
+                let (method, limit) = entry.split_once('=').ok_or_else(|| {
+                    format!("'{entry}' should be '{{rpc_method}}={{limit}}'")
+                })?;
+                Ok((method.trim().to_string(), limit.parse()?))
+            })
+            .collect::<core::result::Result<Vec<_>, String>>()
+            .map(MethodRateLimits)
+    }
+}
+
+// endregion: --- RateLimit
+
+// region:    --- Limiter
+
+/// Who is rate limited.
+#[derive(Debug, Clone, Copy)]
+pub enum RateKey {
+    User(i64),
+    Ip(Option<IpAddr>),
+}
+
+fn store() -> &'static dyn RateLimitStore {
+    static INSTANCE: OnceLock<MemoryStore> = OnceLock::new();
+
+    INSTANCE.get_or_init(MemoryStore::default)
+}
+
+/// Take a token from the caller bucket (for the rpc method, when known),
+/// and returns the wait before the next one when the bucket is empty.
+///
+/// Note: A failing store lets the requests through (after a warning), so
+///       that it does not take the api down.
+pub async fn check(key: RateKey, rpc_method: Option<&str>) -> Option<Duration> {
+    let config = web_config();
+
+    let method_limit = rpc_method.and_then(|rpc_method| {
+        config
+            .RATE_LIMIT_METHODS
+            .get(rpc_method)
+            .map(|limit| (rpc_method, limit))
+    });
+    let (bucket, limit) = match method_limit {
+        Some((rpc_method, limit)) => (rpc_method, limit),
+        None => ("*", &config.RATE_LIMIT_DEFAULT),
+    };
+
+    take(key, bucket, limit).await
+}
+
+/// Take a token from the client ip bucket of the account `route`
+/// (e.g., `/api/register`), limited by `RATE_LIMIT_ACCOUNT`.
+pub async fn check_account(ip: Option<IpAddr>, route: &str) -> Option<Duration> {
+    take(RateKey::Ip(ip), route, &web_config().RATE_LIMIT_ACCOUNT).await
+}
+
+async fn take(key: RateKey, bucket: &str, limit: &RateLimit) -> Option<Duration> {
+    let bucket_key = match key {
+        RateKey::User(user_id) => format!("user:{user_id}:{bucket}"),
+        RateKey::Ip(Some(ip)) => format!("ip:{ip}:{bucket}"),
+        RateKey::Ip(None) => format!("ip:unknown:{bucket}"),
+    };
+
+    match store().take(&bucket_key, limit).await {
+        Ok(retry_after) => retry_after,
+        Err(ex) => {
+            warn!(
+                "{:<12} - store fail, request allowed - {ex:?}",
+                "RATE_LIMIT"
+            );
+            None
+        }
+    }
+}
+
+// endregion: --- Limiter
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_rate_limit_from_str_ok() -> Result<()> {
+        // -- Exec
+        let limit: RateLimit = " 100 / 60 ".parse().map_err(anyhow::Error::msg)?;
+
+        // -- Check
+        assert_eq!(
+            limit,
+            RateLimit {
+                requests: 100,
+                period: Duration::from_secs(60)
+            }
+        );
+        assert!((limit.refill_per_sec() - 100. / 60.).abs() < f64::EPSILON);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limit_from_str_err() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_invalids = ["100", "100/", "a/60", "100/-1", "0/60", "100/0", ""];
+
+        // -- Exec & Check
+        for fx_invalid in fx_invalids {
+            assert!(
+                fx_invalid.parse::<RateLimit>().is_err(),
+                "'{fx_invalid}' should be invalid"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_method_rate_limits_from_str_ok() -> Result<()> {
+        // -- Exec
+        let limits: MethodRateLimits = "create_task=10/60, list_tasks=300/60,"
+            .parse()
+            .map_err(anyhow::Error::msg)?;
+
+        // -- Check
+        assert_eq!(limits.get("create_task").map(|l| l.requests), Some(10));
+        assert_eq!(limits.get("list_tasks").map(|l| l.requests), Some(300));
+        assert!(limits.get("delete_task").is_none());
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
@@ -0,0 +1,143 @@
+//! The `RateLimitStore` implementations (in memory for now).
+
+use crate::rate_limit::RateLimit;
+use crate::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Keeper of the token buckets.
+///
+/// Note: Async, so that a shared store (e.g., redis) can be used
+///       by multiple web-server instances.
+#[async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Take a token from the `key` bucket (created full), and returns the wait
+    /// before the next token when the bucket is empty.
+    async fn take(&self, key: &str, limit: &RateLimit) -> Result<Option<Duration>>;
+}
+
+// region:    --- MemoryStore
+
+/// Number of buckets over which the full ones are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    /// The bucket limit (of its last take), for the prune.
+    capacity: f64,
+    refill_per_sec: f64,
+}
+
+impl Bucket {
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        (self.tokens + elapsed * self.refill_per_sec).min(self.capacity)
+    }
+}
+
+/// Per web-server instance store.
+#[derive(Default)]
+pub struct MemoryStore {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+#[async_trait]
+impl RateLimitStore for MemoryStore {
+    async fn take(&self, key: &str, limit: &RateLimit) -> Result<Option<Duration>> {
+        let now = Instant::now();
+        let capacity = limit.requests as f64;
+        let refill_per_sec = limit.refill_per_sec();
+        let mut buckets = self.buckets.lock().unwrap_or_else(|ex| ex.into_inner());
+
+        if buckets.len() > PRUNE_THRESHOLD {
+            // A bucket refilled by now (with its own limit) is the same
+            // as a new one.
+            buckets.retain(|_, bucket| bucket.tokens_at(now) < bucket.capacity);
+        }
+
+        let bucket = buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+            capacity,
+            refill_per_sec,
+        });
+
+        // -- Refill.
+        bucket.capacity = capacity;
+        bucket.refill_per_sec = refill_per_sec;
+        bucket.tokens = bucket.tokens_at(now);
+        bucket.updated = now;
+
+        // -- Take.
+        if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            Ok(None)
+        } else {
+            let wait_sec = (1. - bucket.tokens) / refill_per_sec;
+            Ok(Some(Duration::from_secs_f64(wait_sec)))
+        }
+    }
+}
+
+// endregion: --- MemoryStore
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[tokio::test]
+    async fn test_memory_store_take_ok_and_empty() -> Result<()> {
+        // -- Setup & Fixtures
+        let store = MemoryStore::default();
+        let fx_limit: RateLimit = "3/60".parse().map_err(anyhow::Error::msg)?;
+
+        // -- Exec
+        let mut waits = Vec::new();
+        for _ in 0..4 {
+            waits.push(store.take("user:1:*", &fx_limit).await?);
+        }
+        let other_wait = store.take("user:2:*", &fx_limit).await?;
+
+        // -- Check
+        assert_eq!(&waits[..3], &[None, None, None]);
+        let wait = waits[3].ok_or_else(|| anyhow::anyhow!("should be empty"))?;
+        // One token every 20 sec.
+        assert!(wait > Duration::from_secs(19) && wait <= Duration::from_secs(20));
+        assert_eq!(other_wait, None, "other key should have its own bucket");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_prune_own_limit() -> Result<()> {
+        // -- Setup & Fixtures
+        let store = MemoryStore::default();
+        let fx_slow: RateLimit = "1/3600".parse().map_err(anyhow::Error::msg)?;
+        let fx_fast: RateLimit = "1/1".parse().map_err(anyhow::Error::msg)?;
+        store.take("user:1:slow", &fx_slow).await?;
+        for i in 0..=PRUNE_THRESHOLD {
+            store.take(&format!("ip:{i}:*"), &fx_fast).await?;
+        }
+
+        // -- Exec
+        // Note: Prunes on this fast take, whose limit would have refilled
+        //       the slow bucket if applied to it.
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        store.take("ip:last:*", &fx_fast).await?;
+        let slow_wait = store.take("user:1:slow", &fx_slow).await?;
+
+        // -- Check
+        assert!(
+            slow_wait.is_some(),
+            "slow bucket should not have been pruned"
+        );
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
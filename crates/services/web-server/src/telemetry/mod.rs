@@ -0,0 +1,84 @@
+//! Tracing setup.
+//!
+//! - The spans and events are printed on the console (filtered by `RUST_LOG`).
+//! - When `SERVICE_OTLP_ENDPOINT` is set (e.g., `http://localhost:4317` for a
+//!   local collector), the spans are also exported with OTLP (grpc), and the
+//!   request spans continue the trace of the incoming `traceparent` header.
+
+use crate::web_config;
+use crate::Result;
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::{runtime, trace, Resource};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter};
+
+const SERVICE_NAME: &str = "web-server";
+
+pub fn init_tracing() -> Result<()> {
+    let otel_layer = match &web_config().OTLP_ENDPOINT {
+        Some(endpoint) => {
+            global::set_text_map_propagator(TraceContextPropagator::new());
+
+            let tracer = opentelemetry_otlp::new_pipeline()
+                .tracing()
+                .with_exporter(
+                    opentelemetry_otlp::new_exporter()
+                        .tonic()
+                        .with_endpoint(endpoint),
+                )
+                .with_trace_config(trace::config().with_resource(Resource::new([
+                    KeyValue::new("service.name", SERVICE_NAME),
+                ])))
+                .install_batch(runtime::Tokio)?;
+
+            Some(tracing_opentelemetry::layer().with_tracer(tracer))
+        }
+        None => None,
+    };
+
+    tracing_subscriber::registry()
+        .with(EnvFilter::from_default_env())
+        .with(
+            fmt::layer()
+                .without_time() // For early local development.
+                .with_target(false),
+        )
+        .with(otel_layer)
+        .init();
+
+    Ok(())
+}
+
+/// Export the pending spans (when the OTLP exporter is enabled).
+pub fn shutdown_tracing() {
+    global::shutdown_tracer_provider();
+}
+
+/// Set the parent of `span` from the `traceparent` header.
+///
+/// Note: No-op when the OTLP exporter is not enabled.
+pub fn set_parent_from_headers(span: &Span, headers: &HeaderMap) {
+    let parent = global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(parent);
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|key| key.as_str()).collect()
+    }
+}
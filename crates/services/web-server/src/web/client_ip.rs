@@ -0,0 +1,103 @@
+use crate::web_config;
+use async_trait::async_trait;
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::HeaderMap;
+use std::convert::Infallible;
+use std::net::{IpAddr, SocketAddr};
+
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+
+/// Client ip of the request.
+///
+/// The rightmost `X-Forwarded-For` address when `TRUST_FORWARDED_FOR` (i.e., behind
+/// a trusted proxy), otherwise the peer address (`None` when not served with the
+/// connect info, e.g., in tests).
+#[derive(Debug, Clone, Copy)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> core::result::Result<Self, Infallible> {
+        if web_config().TRUST_FORWARDED_FOR {
+            if let Some(ip) = forwarded_for_ip(&parts.headers) {
+                return Ok(ClientIp(Some(ip)));
+            }
+        }
+
+        let peer_ip = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        Ok(ClientIp(peer_ip))
+    }
+}
+
+/// The address appended by the trusted proxy, i.e., the last `X-Forwarded-For`
+/// entry.
+///
+/// Note: Not the leftmost entry, which is the one the client sends
+///       (so any client could pick its own ip).
+fn forwarded_for_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    headers
+        .get_all(X_FORWARDED_FOR)
+        .iter()
+        .next_back()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse::<IpAddr>().ok())
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use axum::http::HeaderValue;
+
+    #[test]
+    fn test_forwarded_for_ip_ok_spoofed_leading_entry() -> Result<()> {
+        // -- Setup & Fixtures
+        let mut fx_headers = HeaderMap::new();
+        fx_headers.append(
+            X_FORWARDED_FOR,
+            HeaderValue::from_static("6.6.6.6, 10.0.0.1"),
+        );
+        fx_headers.append(X_FORWARDED_FOR, HeaderValue::from_static("203.0.113.7"));
+
+        // -- Exec
+        let ip = forwarded_for_ip(&fx_headers);
+
+        // -- Check
+        assert_eq!(ip, Some("203.0.113.7".parse()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_forwarded_for_ip_ok_last_of_list() -> Result<()> {
+        // -- Setup & Fixtures
+        let mut fx_headers = HeaderMap::new();
+        fx_headers.insert(
+            X_FORWARDED_FOR,
+            HeaderValue::from_static("6.6.6.6, 7.7.7.7 , 203.0.113.7"),
+        );
+
+        // -- Exec
+        let ip = forwarded_for_ip(&fx_headers);
+
+        // -- Check
+        assert_eq!(ip, Some("203.0.113.7".parse()?));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
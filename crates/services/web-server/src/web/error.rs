@@ -2,8 +2,9 @@ use crate::web;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use derive_more::From;
-use lib_auth::{pwd, token};
+use lib_auth::{oidc, pwd, token};
 use lib_core::model;
+use lib_utils::validate::{self, FieldError};
 use serde::Serialize;
 use serde_with::{serde_as, DisplayFromStr};
 use tracing::debug;
@@ -22,6 +23,66 @@ pub enum Error {
     LoginFailPwdNotMatching {
         user_id: i64,
     },
+    LoginFailChallengeInvalid,
+    LoginFailTotpNotMatching {
+        user_id: i64,
+    },
+    LoginFailUserDisabled {
+        user_id: i64,
+    },
+    LoginLocked {
+        username: String,
+        scope: web::login_guard::LockScope,
+        retry_after_sec: u64,
+        /// True for the failure which started the lockout.
+        is_new: bool,
+    },
+
+    // -- Registration
+    RegistrationDisabled,
+
+    // -- Oidc
+    OidcDisabled,
+    /// The callback has no (or another) flow cookie, or state.
+    OidcFlowInvalid,
+    /// The provider declined the login (e.g., `access_denied`).
+    OidcCallbackDenied {
+        error: String,
+    },
+    OidcCodeExchangeFail {
+        cause: String,
+    },
+    /// The discovery or jwks request failed.
+    OidcProviderFail {
+        cause: String,
+    },
+
+    // -- Pwd Reset
+    PwdResetTokenInvalid,
+
+    // -- Rate Limit
+    RateLimited {
+        rpc_method: Option<String>,
+        retry_after_sec: u64,
+    },
+
+    // -- Rpc
+    RpcFailJsonRequest {
+        cause: String,
+    },
+
+    // -- Sse
+    SseFailJsonFilters {
+        cause: String,
+    },
+
+    // -- Csrf
+    CsrfOriginNotAllowed {
+        origin: Option<String>,
+    },
+
+    // -- ReqStamp
+    ReqStampNotInReqExt,
 
     // -- CtxExtError
     #[from]
@@ -35,6 +96,8 @@ pub enum Error {
     #[from]
     Token(token::Error),
     #[from]
+    Oidc(oidc::Error),
+    #[from]
     Rpc(lib_rpc::Error),
 
     // -- External Modules
@@ -83,18 +146,177 @@ impl Error {
             // -- Login
             LoginFailUsernameNotFound
             | LoginFailUserHasNoPwd { .. }
-            | LoginFailPwdNotMatching { .. } => {
+            | LoginFailPwdNotMatching { .. }
+            | LoginFailChallengeInvalid
+            | LoginFailTotpNotMatching { .. } => {
                 (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL)
             }
+            LoginFailUserDisabled { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::USER_DISABLED)
+            }
+            LoginLocked {
+                retry_after_sec, ..
+            } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ClientError::LOGIN_LOCKED {
+                    retry_after_sec: *retry_after_sec,
+                },
+            ),
+
+            // -- Registration
+            RegistrationDisabled => {
+                (StatusCode::FORBIDDEN, ClientError::REGISTRATION_DISABLED)
+            }
+            // -- Oidc
+            // Note: The provider unavailability (`OidcProviderFail`) is a
+            //       service error.
+            OidcDisabled => (StatusCode::FORBIDDEN, ClientError::OIDC_DISABLED),
+            OidcFlowInvalid
+            | OidcCallbackDenied { .. }
+            | OidcCodeExchangeFail { .. }
+            | Oidc(_) => (StatusCode::FORBIDDEN, ClientError::LOGIN_FAIL),
+
+            Model(model::Error::UserAlreadyExists { username })
+            | Rpc(lib_rpc::Error::Model(model::Error::UserAlreadyExists {
+                username,
+            })) => (
+                StatusCode::CONFLICT,
+                ClientError::USERNAME_ALREADY_EXISTS {
+                    username: username.to_string(),
+                },
+            ),
+
+            // -- Pwd
+            PwdResetTokenInvalid => (
+                StatusCode::BAD_REQUEST,
+                ClientError::PWD_RESET_TOKEN_INVALID,
+            ),
+            Rpc(lib_rpc::Error::Model(model::Error::PwdNotMatching { .. })) => {
+                (StatusCode::BAD_REQUEST, ClientError::PWD_NOT_MATCHING)
+            }
+
+            // -- Rate Limit
+            RateLimited {
+                retry_after_sec, ..
+            } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                ClientError::RATE_LIMITED {
+                    retry_after_sec: *retry_after_sec,
+                },
+            ),
 
             // -- Auth
             CtxExt(_) => (StatusCode::FORBIDDEN, ClientError::NO_AUTH),
+            Rpc(lib_rpc::Error::AdminRequired { .. }) => {
+                (StatusCode::FORBIDDEN, ClientError::ADMIN_REQUIRED)
+            }
+            Rpc(lib_rpc::Error::ImpersonationBlocked { .. }) => {
+                (StatusCode::FORBIDDEN, ClientError::IMPERSONATION_BLOCKED)
+            }
+            Rpc(lib_rpc::Error::ImpersonationTargetInvalid { user_id }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::IMPERSONATION_TARGET_INVALID { user_id: *user_id },
+            ),
+            CsrfOriginNotAllowed { .. } => {
+                (StatusCode::FORBIDDEN, ClientError::CSRF_CHECK_FAIL)
+            }
 
             // -- Model
-            Model(model::Error::EntityNotFound { entity, id }) => (
+            // Note: Model errors can come directly (e.g., from a web handler)
+            //       or wrapped by lib-rpc when raised from an rpc handler.
+            Model(model::Error::EntityNotFound { entity, id })
+            | Rpc(lib_rpc::Error::Model(model::Error::EntityNotFound {
+                entity,
+                id,
+            })) => (
                 StatusCode::BAD_REQUEST,
                 ClientError::ENTITY_NOT_FOUND { entity, id: *id },
             ),
+            Model(model::Error::ListLimitOverMax { max, actual })
+            | Rpc(lib_rpc::Error::Model(model::Error::ListLimitOverMax {
+                max,
+                actual,
+            })) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::LIST_LIMIT_OVER_MAX {
+                    max: *max,
+                    actual: *actual,
+                },
+            ),
+
+            // Note: The totp errors of the login are mapped to the `LoginFail...`
+            //       errors, so these are for the totp rpc methods.
+            Model(model::Error::TotpCodeNotMatching { .. })
+            | Rpc(lib_rpc::Error::Model(model::Error::TotpCodeNotMatching {
+                ..
+            })) => (StatusCode::BAD_REQUEST, ClientError::TOTP_CODE_INVALID),
+            Model(model::Error::TotpAlreadyEnabled { .. })
+            | Rpc(lib_rpc::Error::Model(model::Error::TotpAlreadyEnabled {
+                ..
+            })) => (StatusCode::BAD_REQUEST, ClientError::TOTP_ALREADY_ENABLED),
+            Model(model::Error::TotpNotEnrolled { .. })
+            | Rpc(lib_rpc::Error::Model(model::Error::TotpNotEnrolled { .. })) => {
+                (StatusCode::BAD_REQUEST, ClientError::TOTP_NOT_ENROLLED)
+            }
+
+            Model(model::Error::Timeout)
+            | Rpc(lib_rpc::Error::Model(model::Error::Timeout)) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                ClientError::SERVICE_TIMEOUT,
+            ),
+
+            Model(model::Error::Validate(validate::Error::FieldsInvalid(
+                field_errors,
+            )))
+            | Rpc(lib_rpc::Error::Model(model::Error::Validate(
+                validate::Error::FieldsInvalid(field_errors),
+            )))
+            | Rpc(lib_rpc::Error::RpcParamsInvalid { field_errors, .. }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::VALIDATION_FAILED {
+                    field_errors: field_errors.clone(),
+                },
+            ),
+
+            // -- Sse
+            SseFailJsonFilters { cause } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::SSE_INVALID_FILTERS {
+                    cause: cause.to_string(),
+                },
+            ),
+
+            // -- Rpc
+            RpcFailJsonRequest { cause } => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_REQUEST_INVALID {
+                    cause: cause.to_string(),
+                },
+            ),
+            Rpc(lib_rpc::Error::RpcMethodUnknown(rpc_method)) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_METHOD_UNKNOWN {
+                    rpc_method: rpc_method.to_string(),
+                },
+            ),
+            Rpc(lib_rpc::Error::RpcMissingParams { rpc_method }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_MISSING_PARAMS {
+                    rpc_method: rpc_method.to_string(),
+                },
+            ),
+            Rpc(lib_rpc::Error::RpcFailJsonParams {
+                rpc_method,
+                param_path,
+                cause,
+            }) => (
+                StatusCode::BAD_REQUEST,
+                ClientError::RPC_INVALID_PARAMS {
+                    rpc_method: rpc_method.to_string(),
+                    param_path: param_path.to_string(),
+                    cause: cause.to_string(),
+                },
+            ),
 
             // -- Fallback.
             _ => (
@@ -110,9 +332,76 @@ impl Error {
 #[allow(non_camel_case_types)]
 pub enum ClientError {
     LOGIN_FAIL,
+    LOGIN_LOCKED {
+        retry_after_sec: u64,
+    },
+    USER_DISABLED,
+    REGISTRATION_DISABLED,
+    OIDC_DISABLED,
+    USERNAME_ALREADY_EXISTS {
+        username: String,
+    },
+    PWD_RESET_TOKEN_INVALID,
+    PWD_NOT_MATCHING,
     NO_AUTH,
-    ENTITY_NOT_FOUND { entity: &'static str, id: i64 },
+    ADMIN_REQUIRED,
+    IMPERSONATION_BLOCKED,
+    IMPERSONATION_TARGET_INVALID {
+        user_id: i64,
+    },
+    CSRF_CHECK_FAIL,
+    RATE_LIMITED {
+        retry_after_sec: u64,
+    },
+    ENTITY_NOT_FOUND {
+        entity: &'static str,
+        id: i64,
+    },
+    LIST_LIMIT_OVER_MAX {
+        max: i64,
+        actual: i64,
+    },
+    TOTP_CODE_INVALID,
+    TOTP_ALREADY_ENABLED,
+    TOTP_NOT_ENROLLED,
+
+    RPC_REQUEST_INVALID {
+        cause: String,
+    },
+    RPC_METHOD_UNKNOWN {
+        rpc_method: String,
+    },
+    RPC_MISSING_PARAMS {
+        rpc_method: String,
+    },
+    RPC_INVALID_PARAMS {
+        rpc_method: String,
+        param_path: String,
+        cause: String,
+    },
+    VALIDATION_FAILED {
+        field_errors: Vec<FieldError>,
+    },
 
+    SSE_INVALID_FILTERS {
+        cause: String,
+    },
+
+    SERVICE_TIMEOUT,
     SERVICE_ERROR,
 }
+
+impl ClientError {
+    /// Seconds after which the request can be retried
+    /// (for the `Retry-After` header).
+    pub fn retry_after_sec(&self) -> Option<u64> {
+        match self {
+            ClientError::LOGIN_LOCKED { retry_after_sec }
+            | ClientError::RATE_LIMITED { retry_after_sec } => {
+                Some(*retry_after_sec)
+            }
+            _ => None,
+        }
+    }
+}
 // endregion: --- Client Error
@@ -0,0 +1,255 @@
+//! Login brute-force protection.
+//!
+//! - The failed logins are counted per username and per client ip, and reset
+//!   `LOGIN_LOCKOUT_SEC` after the last failure.
+//! - Each failure delays the next attempts (`LOGIN_DELAY_BASE_MS`, doubled per
+//!   failure, up to `DELAY_MAX`).
+//! - Past `LOGIN_MAX_FAILURES` (username) or `LOGIN_IP_MAX_FAILURES` (ip), the
+//!   logins are rejected for `LOGIN_LOCKOUT_SEC`.
+//!
+//! Note: The usernames are tracked whether or not they exist, so that the
+//!       lockout does not tell them apart.
+//!
+//! Note: The counters are in memory, so per web-server instance
+//!       (and reset on restart).
+
+use crate::web::{Error, Result};
+use crate::web_config;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+const DELAY_MAX: Duration = Duration::from_secs(5);
+/// Number of tracked keys over which the expired ones are pruned.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub enum LockScope {
+    Username,
+    Ip,
+}
+
+struct Attempts {
+    failures: u32,
+    last_failure: Instant,
+    locked_until: Option<Instant>,
+}
+
+impl Attempts {
+    fn new(now: Instant) -> Self {
+        Attempts {
+            failures: 0,
+            last_failure: now,
+            locked_until: None,
+        }
+    }
+
+    /// Count a failure (after the expired ones are forgotten), and returns
+    /// true when it starts the `lockout`.
+    fn add_failure(
+        &mut self,
+        now: Instant,
+        lockout: Duration,
+        max_failures: u32,
+    ) -> bool {
+        if is_expired(self, now, lockout) {
+            *self = Attempts::new(now);
+        }
+        self.failures += 1;
+        self.last_failure = now;
+
+        if self.failures >= max_failures && self.locked_until.is_none() {
+            self.locked_until = Some(now + lockout);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+fn attempts_store() -> &'static Mutex<HashMap<String, Attempts>> {
+    static INSTANCE: OnceLock<Mutex<HashMap<String, Attempts>>> = OnceLock::new();
+
+    INSTANCE.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Check that the login is not locked out,
+/// and returns the delay to apply before validating it.
+pub fn check(username: &str, ip: Option<IpAddr>) -> Result<Duration> {
+    let config = web_config();
+    let now = Instant::now();
+    let lockout = Duration::from_secs(config.LOGIN_LOCKOUT_SEC);
+    let store = attempts_store().lock().unwrap_or_else(|ex| ex.into_inner());
+
+    let mut failures = 0;
+    for (scope, key) in keys(username, ip) {
+        let Some(attempts) = store
+            .get(&key)
+            .filter(|attempts| !is_expired(attempts, now, lockout))
+        else {
+            continue;
+        };
+
+        if let Some(locked_until) = attempts.locked_until.filter(|lu| *lu > now) {
+            return Err(Error::LoginLocked {
+                username: username.to_string(),
+                scope,
+                retry_after_sec: (locked_until - now).as_secs() + 1,
+                is_new: false,
+            });
+        }
+        failures = failures.max(attempts.failures);
+    }
+
+    let delay_base = Duration::from_millis(config.LOGIN_DELAY_BASE_MS);
+
+    Ok(delay(failures, delay_base))
+}
+
+/// Count a failed login, and start the lockout when over the max failures.
+pub fn record_failure(username: &str, ip: Option<IpAddr>) -> Result<()> {
+    let config = web_config();
+    let now = Instant::now();
+    let lockout = Duration::from_secs(config.LOGIN_LOCKOUT_SEC);
+    let mut store = attempts_store().lock().unwrap_or_else(|ex| ex.into_inner());
+
+    if store.len() > PRUNE_THRESHOLD {
+        store.retain(|_, attempts| !is_expired(attempts, now, lockout));
+    }
+
+    let mut lock_error = None;
+    for (scope, key) in keys(username, ip) {
+        let max_failures = match scope {
+            LockScope::Username => config.LOGIN_MAX_FAILURES,
+            LockScope::Ip => config.LOGIN_IP_MAX_FAILURES,
+        };
+
+        let attempts = store.entry(key).or_insert_with(|| Attempts::new(now));
+        if attempts.add_failure(now, lockout, max_failures) {
+            lock_error.get_or_insert(Error::LoginLocked {
+                username: username.to_string(),
+                scope,
+                retry_after_sec: lockout.as_secs(),
+                is_new: true,
+            });
+        }
+    }
+
+    match lock_error {
+        Some(lock_error) => Err(lock_error),
+        None => Ok(()),
+    }
+}
+
+/// Reset the username failures (the ip ones are kept, since other usernames
+/// may be tried from it).
+pub fn record_success(username: &str) {
+    let mut store = attempts_store().lock().unwrap_or_else(|ex| ex.into_inner());
+    store.remove(&username_key(username));
+}
+
+// region:    --- Support
+
+fn keys(username: &str, ip: Option<IpAddr>) -> Vec<(LockScope, String)> {
+    let mut keys = vec![(LockScope::Username, username_key(username))];
+    if let Some(ip) = ip {
+        keys.push((LockScope::Ip, format!("ip:{ip}")));
+    }
+    keys
+}
+
+fn username_key(username: &str) -> String {
+    format!("username:{}", username.to_lowercase())
+}
+
+/// The failures are forgotten `lockout` (`LOGIN_LOCKOUT_SEC`) after the last
+/// one (and so after the lockout end).
+fn is_expired(attempts: &Attempts, now: Instant, lockout: Duration) -> bool {
+    let mut end = attempts.last_failure + lockout;
+    if let Some(locked_until) = attempts.locked_until {
+        end = end.max(locked_until);
+    }
+
+    end <= now
+}
+
+fn delay(failures: u32, base: Duration) -> Duration {
+    if failures == 0 {
+        return Duration::ZERO;
+    }
+
+    let factor = 2u32.saturating_pow(failures - 1);
+
+    base.saturating_mul(factor).min(DELAY_MAX)
+}
+
+// endregion: --- Support
+
+// region:    --- Tests
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn test_delay_doubled_and_capped() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_base = Duration::from_millis(100);
+
+        // -- Exec
+        let delays: Vec<Duration> = (0..=8).map(|f| delay(f, fx_base)).collect();
+
+        // -- Check
+        let delays_ms: Vec<u128> = delays.iter().map(|d| d.as_millis()).collect();
+        assert_eq!(delays_ms, [0, 100, 200, 400, 800, 1600, 3200, 5000, 5000]);
+        assert_eq!(delay(u32::MAX, fx_base), DELAY_MAX);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_failure_lockout() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_lockout = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut attempts = Attempts::new(now);
+
+        // -- Exec & Check
+        assert!(!attempts.add_failure(now, fx_lockout, 3));
+        assert!(!attempts.add_failure(now, fx_lockout, 3));
+        assert!(attempts.add_failure(now, fx_lockout, 3), "should be locked");
+        assert_eq!(attempts.locked_until, Some(now + fx_lockout));
+        // Already locked, so not a new lockout.
+        assert!(!attempts.add_failure(now, fx_lockout, 3));
+        assert_eq!(attempts.failures, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_add_failure_expired_reset() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_lockout = Duration::from_secs(60);
+        let now = Instant::now();
+        let mut attempts = Attempts::new(now);
+        for _ in 0..3 {
+            attempts.add_failure(now, fx_lockout, 3);
+        }
+
+        // -- Exec
+        let later = now + fx_lockout + Duration::from_secs(1);
+        let is_expired_before = is_expired(&attempts, later, fx_lockout);
+        let is_new_lock = attempts.add_failure(later, fx_lockout, 3);
+
+        // -- Check
+        assert!(is_expired_before);
+        assert!(!is_new_lock);
+        assert_eq!(attempts.failures, 1);
+        assert_eq!(attempts.locked_until, None);
+
+        Ok(())
+    }
+}
+// endregion: --- Tests
@@ -1,39 +1,72 @@
 // region:    --- Modules
 
+pub mod client_ip;
 mod error;
+pub mod login_guard;
 pub mod mw_auth;
+pub mod mw_csrf;
+pub mod mw_metrics;
+pub mod mw_rate_limit;
+pub mod mw_req_stamp;
 pub mod mw_res_map;
+pub mod mw_security;
+pub mod oidc;
+pub mod routes_health;
 pub mod routes_login;
+pub mod routes_metrics;
+pub mod routes_oidc;
+pub mod routes_pwd_reset;
+pub mod routes_register;
 pub mod routes_rpc;
+pub mod routes_sse;
 pub mod routes_static;
+pub mod routes_ws;
 
 pub use self::error::ClientError;
 pub use self::error::{Error, Result};
-use lib_auth::token::generate_web_token;
+use crate::web_config;
+use lib_auth::token::{generate_web_token, web_token_duration_sec};
+use tower_cookies::cookie::time::Duration;
 use tower_cookies::{Cookie, Cookies};
 use uuid::Uuid;
 
 // endregion: --- Modules
 
+/// Base name of the auth token cookie
+/// (see `AUTH_COOKIE.name` for the name with the optional `__Host-` prefix).
 pub const AUTH_TOKEN: &str = "auth-token";
 
 fn set_token_cookie(cookies: &Cookies, user: &str, salt: Uuid) -> Result<()> {
     let token = generate_web_token(user, salt)?;
 
-    let mut cookie = Cookie::new(AUTH_TOKEN, token.to_string());
-    cookie.set_http_only(true);
-    cookie.set_path("/");
+    let mut cookie = new_token_cookie(token.to_string());
+    // Note: The token is renewed on each request, so the cookie lives as long
+    //       as the token does.
+    cookie.set_max_age(Duration::seconds(web_token_duration_sec() as i64));
 
     cookies.add(cookie);
 
     Ok(())
 }
 
-fn remove_token_cookie(cookies: &Cookies) -> Result<()> {
-    let mut cookie = Cookie::named(AUTH_TOKEN);
-    cookie.set_path("/");
+/// Note: With the same attributes as `set_token_cookie`, since browsers only
+///       replace (and so remove) a cookie with matching name, path and domain.
+fn remove_token_cookie(cookies: &Cookies) {
+    cookies.remove(new_token_cookie(String::new()));
+}
 
-    cookies.remove(cookie);
+/// Auth token cookie, with the `AUTH_COOKIE` policy of the web config.
+fn new_token_cookie(value: String) -> Cookie<'static> {
+    let config = &web_config().AUTH_COOKIE;
 
-    Ok(())
+    let mut cookie = Cookie::new(config.name.clone(), value);
+    cookie.set_http_only(true);
+    cookie.set_path("/");
+    cookie.set_secure(config.secure);
+    cookie.set_same_site(config.same_site);
+    if let Some(domain) = &config.domain {
+        cookie.set_domain(domain.clone());
+    }
+
+    cookie
 }
@@ -1,18 +1,24 @@
-use crate::web::{set_token_cookie, AUTH_TOKEN};
+use crate::web::mw_req_stamp::ReqStamp;
+use crate::web::{remove_token_cookie, set_token_cookie};
 use crate::web::{Error, Result};
+use crate::web_config;
 use async_trait::async_trait;
 use axum::extract::{FromRequestParts, State};
+use axum::http::header::AUTHORIZATION;
 use axum::http::request::Parts;
-use axum::http::Request;
+use axum::http::{HeaderMap, Request};
 use axum::middleware::Next;
 use axum::response::Response;
-use lib_auth::token::{validate_web_token, Token};
+use lib_auth::token::{
+    impersonation_idents, validate_impersonation_token, validate_web_token, Token,
+};
 use lib_core::ctx::Ctx;
 use lib_core::model::user::{UserBmc, UserForAuth};
-use lib_core::model::ModelManager;
+use lib_core::model::{self, ModelManager};
 use serde::Serialize;
-use tower_cookies::{Cookie, Cookies};
+use tower_cookies::Cookies;
 use tracing::debug;
+use uuid::Uuid;
 
 #[allow(dead_code)] // For now, until we have the rpc.
 pub async fn mw_ctx_require<B>(
@@ -35,12 +41,23 @@ pub async fn mw_ctx_resolve<B>(
 ) -> Result<Response> {
     debug!("{:<12} - mw_ctx_resolve", "MIDDLEWARE");
 
-    let ctx_ext_result = _ctx_resolve(mm, &cookies).await;
+    let req_id = req.extensions().get::<ReqStamp>().map(|rs| rs.uuid);
+    let resolved = _ctx_resolve(mm, &cookies, req.headers(), req_id).await;
+    let ctx_ext_result = match resolved {
+        Ok((ctx, ctx_auth)) => {
+            req.extensions_mut().insert(ctx_auth);
+            Ok(ctx)
+        }
+        Err(ex) => Err(ex),
+    };
 
+    // Note: Not for a bearer token failure (e.g., an ended impersonation),
+    //       the cookie being another session.
     if ctx_ext_result.is_err()
-        && !matches!(ctx_ext_result, Err(CtxExtError::TokenNotInCookie))
+        && !matches!(ctx_ext_result, Err(CtxExtError::TokenNotInRequest))
+        && bearer_token(req.headers()).is_none()
     {
-        cookies.remove(Cookie::named(AUTH_TOKEN))
+        remove_token_cookie(&cookies)
     }
 
     // Store the ctx_ext_result in the request extension
@@ -50,37 +67,197 @@ pub async fn mw_ctx_resolve<B>(
     Ok(next.run(req).await)
 }
 
-async fn _ctx_resolve(mm: State<ModelManager>, cookies: &Cookies) -> CtxExtResult {
+async fn _ctx_resolve(
+    mm: State<ModelManager>,
+    cookies: &Cookies,
+    headers: &HeaderMap,
+    req_id: Option<Uuid>,
+) -> core::result::Result<(CtxW, CtxAuth), CtxExtError> {
     // -- Get Token String
-    let token = cookies
-        .get(AUTH_TOKEN)
-        .map(|c| c.value().to_string())
-        .ok_or(CtxExtError::TokenNotInCookie)?;
+    // Note: The `Authorization: Bearer` header (e.g., for service clients)
+    //       takes precedence over the auth cookie.
+    let bearer = bearer_token(headers);
+    let from_cookie = bearer.is_none();
+    let token = bearer
+        .or_else(|| {
+            cookies
+                .get(&web_config().AUTH_COOKIE.name)
+                .map(|c| c.value().to_string())
+        })
+        .ok_or(CtxExtError::TokenNotInRequest)?;
 
     // -- Parse Token
     let token: Token = token.parse().map_err(|_| CtxExtError::TokenWrongFormat)?;
 
+    if let Some((admin, username)) = impersonation_idents(&token) {
+        return ctx_resolve_impersonation(&mm, &token, admin, username, req_id)
+            .await;
+    }
+
     // -- Get UserForAuth
-    let user: UserForAuth =
-        UserBmc::first_by_username(&Ctx::root_ctx(), &mm, &token.ident)
-            .await
-            .map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?
-            .ok_or(CtxExtError::UserNotFound)?;
+    let user = get_user_for_auth(&mm, &token.ident).await?;
 
     // -- Validate Token
     validate_web_token(&token, user.token_salt)
         .map_err(|_| CtxExtError::FailValidate)?;
 
+    // -- Check the user access
+    // Note: On each request, so that disabling a user ends its sessions.
+    if user.disabled {
+        return Err(CtxExtError::UserDisabled);
+    }
+
     // -- Update Token
-    set_token_cookie(cookies, &user.username, user.token_salt)
-        .map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+    // Note: Only the cookie sessions. A bearer client did not ask for a cookie,
+    //       which the csrf origin check would not cover (bearer exempt).
+    if from_cookie {
+        set_token_cookie(cookies, &user.username, user.token_salt)
+            .map_err(|_| CtxExtError::CannotSetTokenCookie)?;
+    }
 
     // -- Create CtxExtResult
+    let ctx_auth = CtxAuth {
+        token_salt: user.token_salt,
+        impersonator_token_salt: None,
+    };
     Ctx::new(user.id)
-        .map(CtxW)
+        .map(|ctx| ctx.with_admin(user.is_admin))
+        .map(|ctx| match req_id {
+            Some(req_id) => ctx.with_req_id(req_id),
+            None => ctx,
+        })
+        .map(|ctx| (CtxW(ctx), ctx_auth))
         .map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))
 }
 
+/// Resolve the ctx of an impersonation token (given by `impersonate_user`), for
+/// the impersonated user, with the admin as impersonator.
+///
+/// Note: The token is not renewed (i.e., valid for the `TOKEN_DURATION_SEC`
+///       after the impersonation start), and is rejected once the admin stops
+///       (or starts another) impersonation, or is no longer an admin.
+async fn ctx_resolve_impersonation(
+    mm: &ModelManager,
+    token: &Token,
+    admin: &str,
+    username: &str,
+    req_id: Option<Uuid>,
+) -> core::result::Result<(CtxW, CtxAuth), CtxExtError> {
+    // -- Get the admin and user.
+    let admin = get_user_for_auth(mm, admin).await?;
+    let user = get_user_for_auth(mm, username).await?;
+
+    // -- Validate Token
+    validate_impersonation_token(token, admin.token_salt, user.token_salt)
+        .map_err(|_| CtxExtError::FailValidate)?;
+
+    // -- Check the admin and user access
+    check_impersonation_access(&admin, &user)?;
+
+    // -- Create CtxExtResult
+    // Note: Never with admin access, even if the user was made admin since.
+    let ctx_auth = CtxAuth {
+        token_salt: user.token_salt,
+        impersonator_token_salt: Some(admin.token_salt),
+    };
+    Ctx::new(user.id)
+        .map(|ctx| ctx.with_impersonator(admin.id))
+        .map(|ctx| match req_id {
+            Some(req_id) => ctx.with_req_id(req_id),
+            None => ctx,
+        })
+        .map(|ctx| (CtxW(ctx), ctx_auth))
+        .map_err(|ex| CtxExtError::CtxCreateFail(ex.to_string()))
+}
+
+fn check_impersonation_access(
+    admin: &UserForAuth,
+    user: &UserForAuth,
+) -> core::result::Result<(), CtxExtError> {
+    if admin.disabled || user.disabled {
+        return Err(CtxExtError::UserDisabled);
+    }
+    if !admin.is_admin || admin.impersonating_user_id != Some(user.id) {
+        return Err(CtxExtError::ImpersonationEnded);
+    }
+
+    Ok(())
+}
+
+async fn get_user_for_auth(
+    mm: &ModelManager,
+    username: &str,
+) -> core::result::Result<UserForAuth, CtxExtError> {
+    UserBmc::first_by_username(&Ctx::root_ctx(), mm, username)
+        .await
+        .map_err(|ex| CtxExtError::ModelAccessError(ex.to_string()))?
+        .ok_or(CtxExtError::UserNotFound)
+}
+
+// region:    --- Ctx Auth
+
+/// The token salts the ctx was resolved with (for the user, and the
+/// impersonator if any), stored in the request extensions next to the ctx.
+///
+/// Note: For the long lived sessions (e.g., `/api/ws`), which are only
+///       authenticated once, to re-check them (see `check_ctx_auth`).
+#[derive(Debug, Clone)]
+pub struct CtxAuth {
+    token_salt: Uuid,
+    impersonator_token_salt: Option<Uuid>,
+}
+
+/// Check that the ctx users still have the access the ctx was resolved with,
+/// i.e., not disabled, same token salts (e.g., no pwd change), and for an
+/// impersonation, still an admin impersonating the user.
+///
+/// Note: Not the token expiration, which is renewed by the http requests only.
+pub async fn check_ctx_auth(
+    mm: &ModelManager,
+    ctx: &Ctx,
+    ctx_auth: &CtxAuth,
+) -> core::result::Result<(), CtxExtError> {
+    let user = get_user_for_auth_by_id(mm, ctx.user_id()).await?;
+    if user.token_salt != ctx_auth.token_salt {
+        return Err(CtxExtError::FailValidate);
+    }
+
+    match (ctx.impersonator_id(), ctx_auth.impersonator_token_salt) {
+        (Some(impersonator_id), Some(impersonator_token_salt)) => {
+            let admin = get_user_for_auth_by_id(mm, impersonator_id).await?;
+            if admin.token_salt != impersonator_token_salt {
+                return Err(CtxExtError::FailValidate);
+            }
+            check_impersonation_access(&admin, &user)
+        }
+        (None, None) if user.disabled => Err(CtxExtError::UserDisabled),
+        (None, None) => Ok(()),
+        _ => Err(CtxExtError::FailValidate),
+    }
+}
+
+async fn get_user_for_auth_by_id(
+    mm: &ModelManager,
+    id: i64,
+) -> core::result::Result<UserForAuth, CtxExtError> {
+    UserBmc::get(&Ctx::root_ctx(), mm, id)
+        .await
+        .map_err(|ex| match ex {
+            model::Error::EntityNotFound { .. } => CtxExtError::UserNotFound,
+            ex => CtxExtError::ModelAccessError(ex.to_string()),
+        })
+}
+
+// endregion: --- Ctx Auth
+
+pub(crate) fn bearer_token(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| token.trim().to_string())
+}
+
 // region:    --- Ctx Extractor
 #[derive(Debug, Clone)]
 pub struct CtxW(pub Ctx);
@@ -107,10 +284,12 @@ type CtxExtResult = core::result::Result<CtxW, CtxExtError>;
 
 #[derive(Clone, Serialize, Debug)]
 pub enum CtxExtError {
-    TokenNotInCookie,
+    TokenNotInRequest,
     TokenWrongFormat,
 
     UserNotFound,
+    UserDisabled,
+    ImpersonationEnded,
     ModelAccessError(String),
     FailValidate,
     CannotSetTokenCookie,
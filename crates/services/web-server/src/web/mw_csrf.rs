@@ -0,0 +1,79 @@
+//! CSRF protection of the cookie authenticated rpc calls.
+//!
+//! Origin check (rather than a double-submit token, which the web clients would
+//! have to echo): a cookie authenticated `POST` must come from the service origin
+//! (the request `Host`) or one of the `CORS_ALLOWED_ORIGINS`.
+//!
+//! - So must the websocket upgrades (`GET` with `Upgrade: websocket`), which the
+//!   browsers send cross-site with the cookies, and which CORS does not cover
+//!   (i.e., cross-site websocket hijacking).
+//! - Bearer requests are exempt, since a browser does not add the token on its own.
+//! - Requests without `Origin` (non-browser clients) are allowed, unless
+//!   `Sec-Fetch-Site` says they come from another site.
+
+use crate::web::mw_auth::bearer_token;
+use crate::web::{Error, Result};
+use crate::web_config;
+use axum::http::header::{HOST, ORIGIN, UPGRADE};
+use axum::http::{HeaderMap, Method, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use tracing::debug;
+
+const SEC_FETCH_SITE: &str = "sec-fetch-site";
+
+pub async fn mw_csrf_check<B>(req: Request<B>, next: Next<B>) -> Result<Response> {
+    debug!("{:<12} - mw_csrf_check", "MIDDLEWARE");
+
+    let headers = req.headers();
+    let is_checked = req.method() == Method::POST || is_websocket_upgrade(headers);
+    if is_checked && bearer_token(headers).is_none() {
+        check_origin(headers)?;
+    }
+
+    Ok(next.run(req).await)
+}
+
+fn is_websocket_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(UPGRADE)
+        .and_then(|upgrade| upgrade.to_str().ok())
+        .is_some_and(|upgrade| upgrade.eq_ignore_ascii_case("websocket"))
+}
+
+fn check_origin(headers: &HeaderMap) -> Result<()> {
+    let Some(origin) = headers.get(ORIGIN) else {
+        let is_cross_site = headers
+            .get(SEC_FETCH_SITE)
+            .is_some_and(|site| site == "cross-site");
+
+        return if is_cross_site {
+            Err(Error::CsrfOriginNotAllowed { origin: None })
+        } else {
+            Ok(())
+        };
+    };
+
+    let origin = origin.to_str().unwrap_or_default();
+    let origin_host = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"));
+    let is_same_host =
+        origin_host.is_some_and(|origin_host| Some(origin_host) == host(headers));
+    let is_allowed = web_config()
+        .CORS_ALLOWED_ORIGINS
+        .iter()
+        .any(|allowed| allowed == origin);
+
+    if is_same_host || is_allowed {
+        Ok(())
+    } else {
+        Err(Error::CsrfOriginNotAllowed {
+            origin: Some(origin.to_string()),
+        })
+    }
+}
+
+fn host(headers: &HeaderMap) -> Option<&str> {
+    headers.get(HOST).and_then(|host| host.to_str().ok())
+}
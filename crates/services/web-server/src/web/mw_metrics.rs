@@ -0,0 +1,49 @@
+use crate::metrics;
+use crate::web;
+use crate::web::routes_rpc::RpcInfo;
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::time::Instant;
+
+/// Record the http and rpc metrics of the request.
+///
+/// Note: Used as a `route_layer`, so that the matched path is known, and
+///       inside `mw_reponse_map`, since the `RpcInfo` and `web::Error`
+///       response extensions are not kept in the error response it builds.
+pub async fn mw_metrics<B>(
+    matched_path: Option<MatchedPath>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let start = Instant::now();
+    let method = req.method().to_string();
+    let route = matched_path
+        .as_ref()
+        .map(|path| path.as_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    let res = next.run(req).await;
+    let duration = start.elapsed();
+
+    let web_error = res.extensions().get::<web::Error>();
+    let client_status_error = web_error.map(|we| we.client_status_and_error());
+    let status = client_status_error
+        .as_ref()
+        .map(|(status_code, _)| *status_code)
+        .unwrap_or(res.status());
+
+    metrics::observe_http(&method, &route, status.as_u16(), duration);
+    if let Some(rpc_info) = res.extensions().get::<RpcInfo>() {
+        metrics::observe_rpc(&rpc_info.method, web_error.is_none(), duration);
+    }
+    if let (Some(web_error), Some((_, client_error))) =
+        (web_error, &client_status_error)
+    {
+        metrics::observe_error(web_error, client_error);
+    }
+
+    res
+}
@@ -0,0 +1,84 @@
+use crate::rate_limit::{self, RateKey};
+use crate::web::client_ip::ClientIp;
+use crate::web::mw_auth::CtxW;
+use crate::web::{Error, Result};
+use axum::body::{Body, Bytes};
+use axum::extract::FromRequest;
+use axum::http::{Method, Request};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
+use tracing::debug;
+
+/// Rate limit the api requests, per user (or client ip when not authenticated),
+/// and per rpc method for the rpc calls (see `rate_limit`).
+///
+/// Note: The rpc method is peeked from the buffered body, which is then
+///       given back to the request.
+pub async fn mw_rate_limit(
+    ctx: Result<CtxW>,
+    ClientIp(client_ip): ClientIp,
+    req: Request<Body>,
+    next: Next<Body>,
+) -> Result<Response> {
+    debug!("{:<12} - mw_rate_limit", "MIDDLEWARE");
+
+    let key = match ctx {
+        Ok(CtxW(ctx)) => RateKey::User(ctx.user_id()),
+        Err(_) => RateKey::Ip(client_ip),
+    };
+
+    let (req, rpc_method) = if req.method() == Method::POST {
+        let (parts, body) = req.into_parts();
+        let bytes = match Bytes::from_request(Request::new(body), &()).await {
+            Ok(bytes) => bytes,
+            Err(rejection) => return Ok(rejection.into_response()),
+        };
+        let rpc_method = serde_json::from_slice::<RpcMethodPeek>(&bytes)
+            .ok()
+            .map(|peek| peek.method);
+
+        (Request::from_parts(parts, Body::from(bytes)), rpc_method)
+    } else {
+        (req, None)
+    };
+
+    if let Some(retry_after) = rate_limit::check(key, rpc_method.as_deref()).await {
+        return Err(Error::RateLimited {
+            rpc_method,
+            retry_after_sec: retry_after.as_secs() + 1,
+        });
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Rate limit the unauthenticated account routes (e.g., `/api/register`),
+/// per client ip and route, whether or not the caller is authenticated
+/// (see `rate_limit::check_account`).
+///
+/// Note: Route layered, so the path is one of the matched routes
+///       (i.e., a bounded set of buckets).
+pub async fn mw_rate_limit_ip<B>(
+    ClientIp(client_ip): ClientIp,
+    req: Request<B>,
+    next: Next<B>,
+) -> Result<Response> {
+    debug!("{:<12} - mw_rate_limit_ip", "MIDDLEWARE");
+
+    if let Some(retry_after) =
+        rate_limit::check_account(client_ip, req.uri().path()).await
+    {
+        return Err(Error::RateLimited {
+            rpc_method: None,
+            retry_after_sec: retry_after.as_secs() + 1,
+        });
+    }
+
+    Ok(next.run(req).await)
+}
+
+#[derive(Deserialize)]
+struct RpcMethodPeek {
+    method: String,
+}
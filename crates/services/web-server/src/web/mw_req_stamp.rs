@@ -0,0 +1,85 @@
+use crate::telemetry;
+use crate::web::{Error, Result};
+use async_trait::async_trait;
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::time::Instant;
+use tracing::{debug, info_span, Instrument};
+use uuid::Uuid;
+
+const X_REQUEST_ID: &str = "x-request-id";
+
+/// Request stamp, set by the outermost middleware, so that the request id
+/// is known by all of the layers, and the latency includes all of them.
+#[derive(Debug, Clone)]
+pub struct ReqStamp {
+    pub uuid: Uuid,
+    pub time_in: Instant,
+}
+
+pub async fn mw_req_stamp<B>(mut req: Request<B>, next: Next<B>) -> Response {
+    debug!("{:<12} - mw_req_stamp", "MIDDLEWARE");
+
+    let uuid = req_uuid_from_headers(req.headers()).unwrap_or_else(Uuid::new_v4);
+
+    let span = info_span!(
+        "request",
+        req_id = %uuid,
+        http_method = %req.method(),
+        http_path = %req.uri().path(),
+    );
+    telemetry::set_parent_from_headers(&span, req.headers());
+
+    req.extensions_mut().insert(ReqStamp {
+        uuid,
+        time_in: Instant::now(),
+    });
+
+    let mut res = next.run(req).instrument(span).await;
+
+    if let Ok(req_id) = HeaderValue::from_str(&uuid.to_string()) {
+        res.headers_mut().insert(X_REQUEST_ID, req_id);
+    }
+
+    res
+}
+
+/// Returns the request id given by the client, either the trace id of the
+/// `traceparent` header (w3c trace context) or the `x-request-id` header.
+///
+/// Note: `x-request-id` values which are not uuids are ignored, since the
+///       request id is a uuid in the request log.
+fn req_uuid_from_headers(headers: &HeaderMap) -> Option<Uuid> {
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+    // Format: `{version}-{trace_id}-{parent_id}-{flags}` (trace_id is 32 hex)
+    let from_traceparent = header("traceparent")
+        .and_then(|traceparent| traceparent.split('-').nth(1))
+        .filter(|trace_id| trace_id.len() == 32)
+        .and_then(|trace_id| u128::from_str_radix(trace_id, 16).ok())
+        .filter(|trace_id| *trace_id != 0)
+        .map(Uuid::from_u128);
+
+    from_traceparent
+        .or_else(|| header(X_REQUEST_ID).and_then(|id| Uuid::parse_str(id).ok()))
+}
+
+// region:    --- ReqStamp Extractor
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ReqStamp {
+    type Rejection = Error;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self> {
+        debug!("{:<12} - ReqStamp", "EXTRACTOR");
+
+        parts
+            .extensions
+            .get::<ReqStamp>()
+            .cloned()
+            .ok_or(Error::ReqStampNotInReqExt)
+    }
+}
+// endregion: --- ReqStamp Extractor
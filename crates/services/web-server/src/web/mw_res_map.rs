@@ -1,11 +1,13 @@
 use crate::log::log_request;
-use crate::web;
 use crate::web::mw_auth::CtxW;
+use crate::web::mw_req_stamp::ReqStamp;
 use crate::web::routes_rpc::RpcInfo;
-use axum::http::{Method, Uri};
+use crate::web::{self, ClientError};
+use axum::http::header::RETRY_AFTER;
+use axum::http::{HeaderValue, Method, Uri};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
-use serde_json::{json, to_value};
+use serde_json::{json, to_value, Value};
 use tracing::debug;
 use uuid::Uuid;
 
@@ -13,12 +15,13 @@ pub async fn mw_reponse_map(
     ctx: Option<CtxW>,
     uri: Uri,
     req_method: Method,
+    req_stamp: ReqStamp,
     res: Response,
 ) -> Response {
     let ctx = ctx.map(|ctx| ctx.0);
 
     debug!("{:<12} - mw_reponse_map", "RES_MAPPER");
-    let uuid = Uuid::new_v4();
+    let uuid = req_stamp.uuid;
 
     let rpc_info = res.extensions().get::<RpcInfo>();
 
@@ -31,34 +34,35 @@ pub async fn mw_reponse_map(
         client_status_error
             .as_ref()
             .map(|(status_code, client_error)| {
-                let client_error = to_value(client_error).ok();
-                let message = client_error.as_ref().and_then(|v| v.get("message"));
-                let detail = client_error.as_ref().and_then(|v| v.get("detail"));
-
-                let client_error_body = json!({
-					"id": rpc_info.as_ref().map(|rpc| rpc.id.clone()),
-					"error": {
-						"message": message, // Variant name
-						"data": {
-							"req_uuid": uuid.to_string(),
-							"detail": detail
-						},
-					}
-				});
+                let client_error_body = client_error_body(
+                    rpc_info.as_ref().and_then(|rpc| rpc.id.clone()),
+                    client_error,
+                    uuid,
+                );
 
                 debug!("CLIENT ERROR BODY:\n{client_error_body}");
 
                 // Build the new response from the client_error_body
-                (*status_code, Json(client_error_body)).into_response()
+                let mut response =
+                    (*status_code, Json(client_error_body)).into_response();
+                if let Some(retry_after_sec) = client_error.retry_after_sec() {
+                    response
+                        .headers_mut()
+                        .insert(RETRY_AFTER, HeaderValue::from(retry_after_sec));
+                }
+
+                response
             });
 
     // -- Build and log the server log line.
+    // Note: The log line is only queued (see `log`), so it never delays
+    //       nor fails the response.
     let client_error = client_status_error.unzip().1;
-    // TODO: Need to hander if log_request fail (but should not fail request)
     let _ = log_request(
         uuid,
         req_method,
         uri,
+        req_stamp.time_in.elapsed(),
         rpc_info,
         ctx,
         web_error,
@@ -70,3 +74,28 @@ pub async fn mw_reponse_map(
 
     error_response.unwrap_or(res)
 }
+
+/// Build the json-rpc error body sent to the client.
+///
+/// Note: Shared by the http response mapper and the websocket transport
+///       so that both return the same error envelope.
+pub fn client_error_body(
+    rpc_id: Option<Value>,
+    client_error: &ClientError,
+    req_uuid: Uuid,
+) -> Value {
+    let client_error = to_value(client_error).ok();
+    let message = client_error.as_ref().and_then(|v| v.get("message"));
+    let detail = client_error.as_ref().and_then(|v| v.get("detail"));
+
+    json!({
+        "id": rpc_id,
+        "error": {
+            "message": message, // Variant name
+            "data": {
+                "req_uuid": req_uuid.to_string(),
+                "detail": detail
+            },
+        }
+    })
+}
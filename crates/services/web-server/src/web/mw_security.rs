@@ -0,0 +1,62 @@
+use crate::web_config;
+use axum::http::header::{
+    AUTHORIZATION, CONTENT_SECURITY_POLICY, CONTENT_TYPE, REFERRER_POLICY,
+    STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+use axum::http::{HeaderValue, Method, Request};
+use axum::middleware::Next;
+use axum::response::Response;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+/// Set the security headers on all of the responses (api and static files).
+///
+/// Note: Headers already set by a handler are kept.
+pub async fn mw_security_headers<B>(req: Request<B>, next: Next<B>) -> Response {
+    let mut res = next.run(req).await;
+    let config = web_config();
+
+    let headers = res.headers_mut();
+    headers
+        .entry(X_CONTENT_TYPE_OPTIONS)
+        .or_insert(HeaderValue::from_static("nosniff"));
+    headers
+        .entry(X_FRAME_OPTIONS)
+        .or_insert(HeaderValue::from_static("DENY"));
+    headers
+        .entry(REFERRER_POLICY)
+        .or_insert(HeaderValue::from_static("strict-origin-when-cross-origin"));
+    if let Some(csp) = &config.CONTENT_SECURITY_POLICY {
+        headers
+            .entry(CONTENT_SECURITY_POLICY)
+            .or_insert(csp.clone());
+    }
+    // Note: Ignored by the browsers over http, so sent regardless of the tls
+    //       config (e.g., tls terminated by a proxy).
+    if config.HSTS_MAX_AGE_SEC > 0 {
+        let hsts = format!("max-age={}; includeSubDomains", config.HSTS_MAX_AGE_SEC);
+        if let Ok(hsts) = HeaderValue::from_str(&hsts) {
+            headers.entry(STRICT_TRANSPORT_SECURITY).or_insert(hsts);
+        }
+    }
+
+    res
+}
+
+/// Cross-origin policy for the `CORS_ALLOWED_ORIGINS`
+/// (none allowed when empty).
+///
+/// Note: Credentials are allowed, for the cookie authenticated web clients
+///       (the rpc `POST` is then checked by `mw_csrf_check`).
+pub fn cors_layer() -> CorsLayer {
+    let origins = web_config()
+        .CORS_ALLOWED_ORIGINS
+        .iter()
+        .filter_map(|origin| HeaderValue::from_str(origin).ok())
+        .collect::<Vec<_>>();
+
+    CorsLayer::new()
+        .allow_origin(AllowOrigin::list(origins))
+        .allow_methods([Method::GET, Method::POST])
+        .allow_headers([CONTENT_TYPE, AUTHORIZATION])
+        .allow_credentials(true)
+}
@@ -0,0 +1,225 @@
+//! OpenID Connect provider client (of the `OIDC` config), for `routes_oidc`.
+//!
+//! - The provider metadata (discovery) is fetched on first use, and kept.
+//! - The provider jwks is fetched on first use, and again when an id token is
+//!   signed with an unknown key (i.e., key rotation), at most once per
+//!   `JWKS_REFRESH_MIN_INTERVAL`.
+//!
+//! The flow secrets and id token validation are in `lib_auth::oidc`.
+
+use crate::config::OidcConfig;
+use crate::web::{Error, Result};
+use crate::web_config;
+use lib_auth::oidc::{self, IdTokenClaims, JwkSet, OidcFlow};
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OnceCell, RwLock};
+
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+const JWKS_REFRESH_MIN_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The provider of the `OIDC` config (`None` when not configured).
+pub fn oidc_provider() -> Option<&'static OidcProvider> {
+    static INSTANCE: OnceLock<Option<OidcProvider>> = OnceLock::new();
+
+    INSTANCE
+        .get_or_init(|| web_config().OIDC.as_ref().map(OidcProvider::new))
+        .as_ref()
+}
+
+/// The provider metadata used for the login (OIDC Discovery 3).
+#[derive(Debug, Deserialize)]
+struct ProviderMetadata {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+pub struct OidcProvider {
+    config: &'static OidcConfig,
+    client: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks: RwLock<Option<(Arc<JwkSet>, Instant)>>,
+}
+
+impl OidcProvider {
+    fn new(config: &'static OidcConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .build()
+            .unwrap_or_else(|ex| {
+                panic!("FATAL - WHILE BUILDING OIDC HTTP CLIENT - Cause: {ex:?}")
+            });
+
+        OidcProvider {
+            config,
+            client,
+            metadata: OnceCell::new(),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    pub fn issuer(&self) -> &str {
+        &self.config.issuer
+    }
+
+    /// The provider login url (authorization request), for the flow.
+    pub async fn authorization_url(&self, flow: &OidcFlow) -> Result<String> {
+        let metadata = self.metadata().await?;
+        let config = self.config;
+        let pkce_challenge = flow.pkce_challenge();
+
+        let url = reqwest::Url::parse_with_params(
+            &metadata.authorization_endpoint,
+            [
+                ("response_type", "code"),
+                ("client_id", config.client_id.as_str()),
+                ("redirect_uri", config.redirect_url.as_str()),
+                ("scope", config.scopes.as_str()),
+                ("state", flow.state.as_str()),
+                ("nonce", flow.nonce.as_str()),
+                ("code_challenge", pkce_challenge.as_str()),
+                ("code_challenge_method", "S256"),
+            ],
+        )
+        .map_err(|ex| Error::OidcProviderFail {
+            cause: ex.to_string(),
+        })?;
+
+        Ok(url.into())
+    }
+
+    /// Exchange the callback code (with the flow pkce verifier) for the id
+    /// token, and returns its claims once validated.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        flow: &OidcFlow,
+    ) -> Result<IdTokenClaims> {
+        let metadata = self.metadata().await?;
+        let config = self.config;
+        let exchange_fail = |ex: reqwest::Error| Error::OidcCodeExchangeFail {
+            cause: ex.to_string(),
+        };
+
+        let token_res: TokenResponse = self
+            .client
+            .post(&metadata.token_endpoint)
+            .basic_auth(&config.client_id, Some(&config.client_secret))
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", config.redirect_url.as_str()),
+                ("code_verifier", flow.pkce_verifier.as_str()),
+            ])
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(exchange_fail)?
+            .json()
+            .await
+            .map_err(exchange_fail)?;
+
+        self.validate_id_token(&token_res.id_token, &flow.nonce)
+            .await
+    }
+
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims> {
+        let config = self.config;
+        let validate = |jwks: &JwkSet| {
+            oidc::validate_id_token(
+                id_token,
+                jwks,
+                &config.issuer,
+                &config.client_id,
+                nonce,
+            )
+        };
+
+        match validate(&*self.jwks(false).await?) {
+            Err(oidc::Error::IdTokenKeyNotFound { .. }) => {
+                Ok(validate(&*self.jwks(true).await?)?)
+            }
+            res => Ok(res?),
+        }
+    }
+
+    async fn metadata(&self) -> Result<&ProviderMetadata> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer.trim_end_matches('/')
+                );
+                let metadata: ProviderMetadata = self.get_json(&url).await?;
+
+                // Note: Must be the configured issuer (OIDC Discovery 4.3).
+                if metadata.issuer != self.config.issuer {
+                    return Err(Error::OidcProviderFail {
+                        cause: format!("issuer not matching: {}", metadata.issuer),
+                    });
+                }
+
+                Ok(metadata)
+            })
+            .await
+    }
+
+    /// The cached jwks, fetched again when `refresh` (unless it was within
+    /// the `JWKS_REFRESH_MIN_INTERVAL`).
+    async fn jwks(&self, refresh: bool) -> Result<Arc<JwkSet>> {
+        let cached = |cache: &Option<(Arc<JwkSet>, Instant)>| match cache {
+            Some((jwks, fetched_at))
+                if !refresh || fetched_at.elapsed() < JWKS_REFRESH_MIN_INTERVAL =>
+            {
+                Some(jwks.clone())
+            }
+            _ => None,
+        };
+
+        if let Some(jwks) = cached(&*self.jwks.read().await) {
+            return Ok(jwks);
+        }
+
+        let mut cache = self.jwks.write().await;
+        // Note: Checked again, since another request may have fetched it
+        //       while waiting for the lock.
+        if let Some(jwks) = cached(&cache) {
+            return Ok(jwks);
+        }
+
+        let metadata = self.metadata().await?;
+        let jwks: Arc<JwkSet> = Arc::new(self.get_json(&metadata.jwks_uri).await?);
+        *cache = Some((jwks.clone(), Instant::now()));
+
+        Ok(jwks)
+    }
+
+    async fn get_json<T: DeserializeOwned>(&self, url: &str) -> Result<T> {
+        let provider_fail = |ex: reqwest::Error| Error::OidcProviderFail {
+            cause: ex.to_string(),
+        };
+
+        self.client
+            .get(url)
+            .send()
+            .await
+            .and_then(|res| res.error_for_status())
+            .map_err(provider_fail)?
+            .json()
+            .await
+            .map_err(provider_fail)
+    }
+}
@@ -0,0 +1,139 @@
+//! Liveness and readiness probes.
+//!
+//! - `GET /healthz` returns 200 as long as the process serves requests.
+//! - `GET /readyz` runs the `db`, `migrations` and `config` checks, and returns
+//!   200 when all of them pass, or 503 otherwise, with the status of each check.
+//!
+//! Note: These routes are merged after the middleware layers (see `main.rs`),
+//!       so they are not authenticated and are not request logged. Hence the
+//!       fixed failure details, the causes (e.g., db errors, paths) being
+//!       logged instead.
+
+use crate::web_config;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::routing::get;
+use axum::{Json, Router};
+use lib_core::model::ModelManager;
+use serde::Serialize;
+use serde_json::{json, Value};
+use serde_with::skip_serializing_none;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+use tracing::warn;
+
+const DB_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .with_state(mm)
+}
+
+async fn healthz_handler() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+async fn readyz_handler(State(mm): State<ModelManager>) -> (StatusCode, Json<Value>) {
+    let db = check_db(&mm).await;
+    // Note: The schema is only checked when the db is reachable.
+    let migrations = if db.ok {
+        check_migrations(&mm).await
+    } else {
+        Check::fail("db not reachable")
+    };
+    let config = check_config();
+
+    let ready = db.ok && migrations.ok && config.ok;
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    let body = json!({
+        "status": if ready { "ok" } else { "fail" },
+        "checks": {
+            "db": db,
+            "migrations": migrations,
+            "config": config,
+        }
+    });
+
+    (status, Json(body))
+}
+
+// region:    --- Checks
+
+#[skip_serializing_none]
+#[derive(Serialize)]
+struct Check {
+    ok: bool,
+    detail: Option<String>,
+}
+
+impl Check {
+    fn ok() -> Self {
+        Check {
+            ok: true,
+            detail: None,
+        }
+    }
+
+    fn fail(detail: impl Into<String>) -> Self {
+        Check {
+            ok: false,
+            detail: Some(detail.into()),
+        }
+    }
+}
+
+async fn check_db(mm: &ModelManager) -> Check {
+    match timeout(DB_CHECK_TIMEOUT, mm.ping_db()).await {
+        Ok(Ok(())) => Check::ok(),
+        Ok(Err(ex)) => {
+            warn!("{:<12} - db check fail - {ex}", "READYZ");
+            Check::fail("db error")
+        }
+        Err(_) => Check::fail(format!("timeout after {DB_CHECK_TIMEOUT:?}")),
+    }
+}
+
+async fn check_migrations(mm: &ModelManager) -> Check {
+    match mm.missing_tables().await {
+        Ok(missing) if missing.is_empty() => Check::ok(),
+        Ok(missing) => Check::fail(format!("missing tables: {}", missing.join(", "))),
+        Err(ex) => {
+            warn!("{:<12} - migrations check fail - {ex}", "READYZ");
+            Check::fail("db error")
+        }
+    }
+}
+
+fn check_config() -> Check {
+    let config = web_config();
+
+    if !Path::new(&config.WEB_FOLDER).is_dir() {
+        warn!(
+            "{:<12} - web folder not found: {}",
+            "READYZ", config.WEB_FOLDER
+        );
+        return Check::fail("web folder not found");
+    }
+
+    for path in [&config.TLS_CERT_PATH, &config.TLS_KEY_PATH]
+        .into_iter()
+        .flatten()
+    {
+        if !Path::new(path).is_file() {
+            warn!("{:<12} - tls file not found: {path}", "READYZ");
+            return Check::fail("tls file not found");
+        }
+    }
+
+    Check::ok()
+}
+
+// endregion: --- Checks
@@ -1,19 +1,26 @@
+use crate::metrics;
+use crate::web::client_ip::ClientIp;
+use crate::web::login_guard;
 use crate::web::{self, remove_token_cookie, Error, Result};
 use axum::extract::State;
 use axum::routing::post;
 use axum::{Json, Router};
 use lib_auth::pwd::{self, ContentToHash};
+use lib_auth::token::{generate_login_challenge, validate_login_challenge, Token};
 use lib_core::ctx::Ctx;
 use lib_core::model::user::{UserBmc, UserForLogin};
-use lib_core::model::ModelManager;
+use lib_core::model::{self, ModelManager};
 use serde::Deserialize;
 use serde_json::{json, Value};
+use std::net::IpAddr;
 use tower_cookies::Cookies;
 use tracing::debug;
+use uuid::Uuid;
 
 pub fn routes(mm: ModelManager) -> Router {
     Router::new()
         .route("/api/login", post(api_login_handler))
+        .route("/api/login/totp", post(api_login_totp_handler))
         .route("/api/logoff", post(api_logoff_handler))
         .with_state(mm)
 }
@@ -22,10 +29,33 @@ pub fn routes(mm: ModelManager) -> Router {
 async fn api_login_handler(
     State(mm): State<ModelManager>,
     cookies: Cookies,
+    ClientIp(client_ip): ClientIp,
     Json(payload): Json<LoginPayload>,
 ) -> Result<Json<Value>> {
     debug!("{:<12} - api_login_handler", "HANDLER");
 
+    let username = payload.username.clone();
+
+    // -- Reject when locked out, and delay after the previous failures.
+    let delay = login_guard::check(&username, client_ip)?;
+    tokio::time::sleep(delay).await;
+
+    let res = _api_login_handler(mm, &cookies, payload).await;
+
+    // Note: The password step of a second factor login is not a login yet
+    //       (i.e., the failures are only reset once the second factor is verified).
+    if !matches!(res, Ok(LoginStep::SecondFactor { .. })) {
+        record_login_res(&username, client_ip, &res)?;
+    }
+
+    res.map(LoginStep::into_body)
+}
+
+async fn _api_login_handler(
+    mm: ModelManager,
+    cookies: &Cookies,
+    payload: LoginPayload,
+) -> Result<LoginStep> {
     let LoginPayload {
         username,
         pwd: pwd_clear,
@@ -33,13 +63,17 @@ async fn api_login_handler(
     let root_ctx = Ctx::root_ctx();
 
     // -- Get the user.
-    let user: UserForLogin = UserBmc::first_by_username(&root_ctx, &mm, &username)
-        .await?
-        .ok_or(Error::LoginFailUsernameNotFound)?;
+    let Some(user): Option<UserForLogin> =
+        UserBmc::first_by_username(&root_ctx, &mm, &username).await?
+    else {
+        hash_dummy_pwd(pwd_clear);
+        return Err(Error::LoginFailUsernameNotFound);
+    };
     let user_id = user.id;
 
     // -- Validate the password.
     let Some(pwd) = user.pwd else {
+        hash_dummy_pwd(pwd_clear);
         return Err(Error::LoginFailUserHasNoPwd { user_id });
     };
 
@@ -50,19 +84,86 @@ async fn api_login_handler(
         },
         &pwd,
     )
-        .map_err(|_| Error::LoginFailPwdNotMatching { user_id })?;
+    .map_err(|_| Error::LoginFailPwdNotMatching { user_id })?;
+
+    // Note: After the password, so that only the user knows it is disabled.
+    if user.disabled {
+        return Err(Error::LoginFailUserDisabled { user_id });
+    }
+
+    // -- Challenge the second factor (when enabled).
+    if user.totp_enabled {
+        let challenge = generate_login_challenge(&user.username, user.token_salt)?;
+        return Ok(LoginStep::SecondFactor {
+            challenge: challenge.to_string(),
+        });
+    }
 
     // -- Set web token.
-    web::set_token_cookie(&cookies, &user.username, user.token_salt)?;
+    web::set_token_cookie(cookies, &user.username, user.token_salt)?;
 
-    // Create the success body.
-    let body = Json(json!({
-		"result": {
-			"success": true
-		}
-	}));
+    Ok(LoginStep::Done)
+}
 
-    Ok(body)
+/// Hash the password as `validate_pwd` does, so that the login fails in the
+/// same time whether or not the user (or its password) exists.
+fn hash_dummy_pwd(pwd_clear: String) {
+    let _ = pwd::hash_pwd(&ContentToHash {
+        content: pwd_clear,
+        salt: Uuid::nil(),
+    });
+}
+
+/// Record the login result in the metrics and the login guard.
+fn record_login_res<T>(
+    username: &str,
+    client_ip: Option<IpAddr>,
+    res: &Result<T>,
+) -> Result<()> {
+    metrics::observe_login(res.is_ok());
+
+    match res {
+        Ok(_) => login_guard::record_success(username),
+        // Note: The lockout error (when this failure starts one) replaces the
+        //       login fail error, so that it is in the request log.
+        Err(
+            Error::LoginFailUsernameNotFound
+            | Error::LoginFailUserHasNoPwd { .. }
+            | Error::LoginFailPwdNotMatching { .. }
+            | Error::LoginFailTotpNotMatching { .. },
+        ) => login_guard::record_failure(username, client_ip)?,
+        Err(_) => (),
+    }
+
+    Ok(())
+}
+
+/// The outcome of the password step of the login.
+enum LoginStep {
+    /// Logged in (i.e., the token cookie is set).
+    Done,
+    /// The second factor is required, to be given with the `challenge`
+    /// at `/api/login/totp`.
+    SecondFactor { challenge: String },
+}
+
+impl LoginStep {
+    fn into_body(self) -> Json<Value> {
+        match self {
+            LoginStep::Done => Json(json!({
+                "result": {
+                    "success": true
+                }
+            })),
+            LoginStep::SecondFactor { challenge } => Json(json!({
+                "result": {
+                    "success": false,
+                    "second_factor_required": true,
+                    "challenge": challenge
+                }
+            })),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -72,6 +173,73 @@ struct LoginPayload {
 }
 // endregion: --- Login
 
+// region:    --- Login Second Factor
+async fn api_login_totp_handler(
+    State(mm): State<ModelManager>,
+    cookies: Cookies,
+    ClientIp(client_ip): ClientIp,
+    Json(payload): Json<LoginTotpPayload>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_login_totp_handler", "HANDLER");
+
+    let LoginTotpPayload { challenge, code } = payload;
+    let root_ctx = Ctx::root_ctx();
+
+    // -- Validate the challenge (of the password step).
+    // Note: Before the login guard, so that a forged challenge cannot count
+    //       failures against (and lock out) another user.
+    let challenge: Token = challenge
+        .parse()
+        .map_err(|_| Error::LoginFailChallengeInvalid)?;
+    let user: UserForLogin =
+        UserBmc::first_by_username(&root_ctx, &mm, &challenge.ident)
+            .await?
+            .ok_or(Error::LoginFailChallengeInvalid)?;
+    validate_login_challenge(&challenge, user.token_salt)
+        .map_err(|_| Error::LoginFailChallengeInvalid)?;
+    let user_id = user.id;
+    if user.disabled {
+        return Err(Error::LoginFailUserDisabled { user_id });
+    }
+
+    // -- Reject when locked out, and delay after the previous failures.
+    let delay = login_guard::check(&user.username, client_ip)?;
+    tokio::time::sleep(delay).await;
+
+    // -- Verify the second factor, and set the web token.
+    let res = UserBmc::totp_verify(&root_ctx, &mm, user_id, &code)
+        .await
+        .map_err(|ex| match ex {
+            model::Error::TotpCodeNotMatching { user_id } => {
+                Error::LoginFailTotpNotMatching { user_id }
+            }
+            model::Error::TotpNotEnabled { .. } => Error::LoginFailChallengeInvalid,
+            ex => Error::Model(ex),
+        })
+        .and_then(|_| {
+            web::set_token_cookie(&cookies, &user.username, user.token_salt)
+        });
+
+    record_login_res(&user.username, client_ip, &res)?;
+    res?;
+
+    // Create the success body.
+    let body = Json(json!({
+        "result": {
+            "success": true
+        }
+    }));
+
+    Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+struct LoginTotpPayload {
+    challenge: String,
+    code: String, // totp code, or recovery code
+}
+// endregion: --- Login Second Factor
+
 // region:    --- Logoff
 async fn api_logoff_handler(
     cookies: Cookies,
@@ -81,15 +249,15 @@ async fn api_logoff_handler(
     let should_logoff = payload.logoff;
 
     if should_logoff {
-        remove_token_cookie(&cookies)?;
+        remove_token_cookie(&cookies);
     }
 
     // Create the success body.
     let body = Json(json!({
-		"result": {
-			"logged_off": should_logoff
-		}
-	}));
+        "result": {
+            "logged_off": should_logoff
+        }
+    }));
 
     Ok(body)
 }
@@ -0,0 +1,24 @@
+use crate::metrics;
+use axum::extract::State;
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use lib_core::model::ModelManager;
+
+// Note: Like the health probes, merged after the middleware layers
+//       (not authenticated, and not request logged).
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics_handler))
+        .with_state(mm)
+}
+
+async fn metrics_handler(State(mm): State<ModelManager>) -> impl IntoResponse {
+    let body = metrics::export(&mm);
+
+    (
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        body,
+    )
+}
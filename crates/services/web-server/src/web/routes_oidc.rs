@@ -0,0 +1,280 @@
+use crate::metrics;
+use crate::web::oidc::oidc_provider;
+use crate::web::{self, Error, Result};
+use crate::web_config;
+use axum::extract::{Query, State};
+use axum::response::Redirect;
+use axum::routing::get;
+use axum::Router;
+use lib_auth::oidc::{IdTokenClaims, OidcFlow};
+use lib_auth::token::generate_login_challenge;
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{UserBmc, UserForLogin, UserForOidcCreate};
+use lib_core::model::{self, ModelManager};
+use serde::Deserialize;
+use tower_cookies::cookie::time::Duration;
+use tower_cookies::cookie::SameSite;
+use tower_cookies::{Cookie, Cookies};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Cookie of the login flow, between the login redirect and the callback.
+const OIDC_FLOW: &str = "oidc-flow";
+const FLOW_MAX_AGE_SEC: i64 = 600; // 10 minutes to log in at the provider.
+/// Where the browser is sent once logged in.
+const LOGIN_REDIRECT: &str = "/";
+/// Where the browser is sent for the second factor, followed by the login
+/// challenge (to be given with the code at `/api/login/totp`).
+///
+/// Note: In the url fragment, so that the challenge is not sent to the server
+///       (e.g., in the request logs).
+const LOGIN_TOTP_REDIRECT: &str = "/#login-totp=";
+/// Max length of the username taken from the claims (before the suffix).
+const USERNAME_BASE_MAX_LEN: usize = 48;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/api/oidc/login", get(api_oidc_login_handler))
+        .route("/api/oidc/callback", get(api_oidc_callback_handler))
+        .with_state(mm)
+}
+
+// region:    --- Login
+/// Redirect the browser to the provider login, with a new flow
+/// (kept in the flow cookie until the callback).
+async fn api_oidc_login_handler(cookies: Cookies) -> Result<Redirect> {
+    debug!("{:<12} - api_oidc_login_handler", "HANDLER");
+
+    let provider = oidc_provider().ok_or(Error::OidcDisabled)?;
+
+    let flow = OidcFlow::generate();
+    let url = provider.authorization_url(&flow).await?;
+    cookies.add(new_flow_cookie(flow.to_string()));
+
+    Ok(Redirect::to(&url))
+}
+// endregion: --- Login
+
+// region:    --- Callback
+/// The provider redirect back, which logs in the user of the id token subject
+/// (created on its first login).
+async fn api_oidc_callback_handler(
+    State(mm): State<ModelManager>,
+    cookies: Cookies,
+    Query(params): Query<CallbackParams>,
+) -> Result<Redirect> {
+    debug!("{:<12} - api_oidc_callback_handler", "HANDLER");
+
+    let res = _api_oidc_callback_handler(mm, &cookies, params).await;
+    metrics::observe_login(res.is_ok());
+    let redirect = res?;
+
+    Ok(Redirect::to(&redirect))
+}
+
+async fn _api_oidc_callback_handler(
+    mm: ModelManager,
+    cookies: &Cookies,
+    params: CallbackParams,
+) -> Result<String> {
+    let provider = oidc_provider().ok_or(Error::OidcDisabled)?;
+
+    // -- Check the flow (used once, i.e., removed whatever the outcome).
+    let flow: Option<OidcFlow> = cookies
+        .get(OIDC_FLOW)
+        .and_then(|cookie| cookie.value().parse().ok());
+    cookies.remove(new_flow_cookie(String::new()));
+    let flow = flow.ok_or(Error::OidcFlowInvalid)?;
+
+    let CallbackParams { code, state, error } = params;
+    if state.as_deref() != Some(flow.state.as_str()) {
+        return Err(Error::OidcFlowInvalid);
+    }
+    if let Some(error) = error {
+        return Err(Error::OidcCallbackDenied { error });
+    }
+    let code = code.ok_or(Error::OidcFlowInvalid)?;
+
+    // -- Get the user of the validated id token.
+    let claims = provider.exchange_code(&code, &flow).await?;
+    let user = get_or_create_user(&mm, provider.issuer(), claims).await?;
+    if user.disabled {
+        return Err(Error::LoginFailUserDisabled { user_id: user.id });
+    }
+
+    login_redirect(cookies, &user)
+}
+
+/// Log in the user (setting the web token), or, with totp enabled, challenge
+/// its second factor as the password login does (see `routes_login`).
+///
+/// Returns where to redirect the browser.
+fn login_redirect(cookies: &Cookies, user: &UserForLogin) -> Result<String> {
+    // -- Challenge the second factor (when enabled).
+    if user.totp_enabled {
+        let challenge = generate_login_challenge(&user.username, user.token_salt)?;
+        return Ok(format!("{LOGIN_TOTP_REDIRECT}{challenge}"));
+    }
+
+    // -- Set web token.
+    web::set_token_cookie(cookies, &user.username, user.token_salt)?;
+
+    Ok(LOGIN_REDIRECT.to_string())
+}
+
+/// The user of the id token subject, created on its first login.
+///
+/// Note: The username is taken from the `preferred_username` (or email), with
+///       a random suffix when it is already taken (e.g., by a local user).
+///
+/// Note: A subject conflict on the create is a concurrent first login of the
+///       same subject, whose user is then returned.
+async fn get_or_create_user(
+    mm: &ModelManager,
+    issuer: &str,
+    claims: IdTokenClaims,
+) -> Result<UserForLogin> {
+    let root_ctx = Ctx::root_ctx();
+
+    if let Some(user) =
+        UserBmc::first_by_oidc_subject(&root_ctx, mm, issuer, &claims.sub).await?
+    {
+        return Ok(user);
+    }
+
+    let username = username_base(&claims);
+    let email = claims.email.filter(|_| claims.email_verified);
+    let user_c = |username: String| UserForOidcCreate {
+        username,
+        email: email.clone(),
+        oidc_issuer: issuer.to_string(),
+        oidc_subject: claims.sub.clone(),
+    };
+
+    let mut res =
+        UserBmc::create_oidc(&root_ctx, mm, user_c(username.clone())).await;
+    if let Err(model::Error::UserAlreadyExists { .. }) = res {
+        let suffix = &Uuid::new_v4().simple().to_string()[..6];
+        let username = format!("{username}-{suffix}");
+        res = UserBmc::create_oidc(&root_ctx, mm, user_c(username)).await;
+    }
+
+    match res {
+        Ok(id) => Ok(UserBmc::get(&root_ctx, mm, id).await?),
+        Err(ex @ model::Error::OidcSubjectAlreadyExists { .. }) => {
+            UserBmc::first_by_oidc_subject(&root_ctx, mm, issuer, &claims.sub)
+                .await?
+                .ok_or(Error::Model(ex))
+        }
+        Err(ex) => Err(ex.into()),
+    }
+}
+
+/// Username from the claims, with the username chars only
+/// (e.g., `John.Doe@example.com` gives `john.doe`).
+fn username_base(claims: &IdTokenClaims) -> String {
+    let name = claims
+        .preferred_username
+        .as_deref()
+        .or(claims.email.as_deref())
+        .and_then(|name| name.split('@').next())
+        .unwrap_or_default()
+        .to_lowercase();
+
+    let base: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'))
+        .skip_while(|c| !c.is_ascii_lowercase())
+        .take(USERNAME_BASE_MAX_LEN)
+        .collect();
+
+    if base.len() < 3 {
+        "user".to_string()
+    } else {
+        base
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CallbackParams {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+// endregion: --- Callback
+
+/// Flow cookie, only sent to the `/api/oidc` routes.
+///
+/// Note: `SameSite=Lax` (whatever the `AUTH_COOKIE` policy), since the callback
+///       is a cross-site navigation (from the provider).
+fn new_flow_cookie(value: String) -> Cookie<'static> {
+    let mut cookie = Cookie::new(OIDC_FLOW, value);
+    cookie.set_http_only(true);
+    cookie.set_path("/api/oidc");
+    cookie.set_secure(web_config().AUTH_COOKIE.secure);
+    cookie.set_same_site(SameSite::Lax);
+    cookie.set_max_age(Duration::seconds(FLOW_MAX_AGE_SEC));
+
+    cookie
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use lib_auth::token::{validate_login_challenge, Token};
+
+    fn fx_user(totp_enabled: bool) -> UserForLogin {
+        UserForLogin {
+            id: 1000,
+            username: "test_login_redirect-user".to_string(),
+            pwd: None,
+            pwd_salt: Uuid::new_v4(),
+            token_salt: Uuid::new_v4(),
+            totp_enabled,
+            disabled: false,
+        }
+    }
+
+    #[test]
+    fn test_login_redirect_ok_totp_challenged() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_user = fx_user(true);
+        let cookies = Cookies::default();
+
+        // -- Exec
+        let redirect = login_redirect(&cookies, &fx_user)?;
+
+        // -- Check
+        // Not logged in, until the second factor.
+        assert!(cookies.get(&web_config().AUTH_COOKIE.name).is_none());
+        let challenge = redirect
+            .strip_prefix(LOGIN_TOTP_REDIRECT)
+            .ok_or_else(|| anyhow::anyhow!("should be the totp redirect"))?;
+        let challenge: Token = challenge.parse()?;
+        assert_eq!(challenge.ident, fx_user.username);
+        validate_login_challenge(&challenge, fx_user.token_salt)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_login_redirect_ok_without_totp() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_user = fx_user(false);
+        let cookies = Cookies::default();
+
+        // -- Exec
+        let redirect = login_redirect(&cookies, &fx_user)?;
+
+        // -- Check
+        assert_eq!(redirect, LOGIN_REDIRECT);
+        assert!(cookies.get(&web_config().AUTH_COOKIE.name).is_some());
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
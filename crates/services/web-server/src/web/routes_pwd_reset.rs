@@ -0,0 +1,164 @@
+use crate::mail::{self, Mail};
+use crate::web::{Error, Result};
+use crate::web_config;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use lib_auth::token::{generate_pwd_reset_token, validate_pwd_reset_token, Token};
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{UserBmc, UserForPwdReset};
+use lib_core::model::{self, ModelManager};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Number of tracked usernames over which the cooled down ones are pruned.
+const COOLDOWN_PRUNE_THRESHOLD: usize = 10_000;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route(
+            "/api/pwd_reset/request",
+            post(api_pwd_reset_request_handler),
+        )
+        .route("/api/pwd_reset", post(api_pwd_reset_handler))
+        .with_state(mm)
+}
+
+// region:    --- Reset Request
+/// Mail a pwd reset link (with a reset token) to the user email.
+///
+/// Note: Always succeeds (even for an unknown user, or a user without email),
+///       so that the users cannot be enumerated.
+///
+/// Note: At most one mail per username every `PWD_RESET_COOLDOWN_SEC` (the
+///       others are silently dropped), so that a user mailbox cannot be
+///       flooded (the route being rate limited per ip as well).
+async fn api_pwd_reset_request_handler(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<PwdResetRequestPayload>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_pwd_reset_request_handler", "HANDLER");
+
+    if !cooldown_take(&payload.username) {
+        debug!("{:<12} - pwd reset in cooldown, not mailed", "HANDLER");
+        return Ok(success_body());
+    }
+
+    let root_ctx = Ctx::root_ctx();
+
+    let user: Option<UserForPwdReset> =
+        UserBmc::first_by_username(&root_ctx, &mm, &payload.username).await?;
+
+    if let Some(UserForPwdReset {
+        username,
+        email: Some(email),
+        token_salt,
+        ..
+    }) = user
+    {
+        let token = generate_pwd_reset_token(&username, token_salt)?;
+        let url = format!("{}{token}", web_config().PWD_RESET_URL);
+        let body = format!(
+            "Hello {username},\n\n\
+             To reset your password, open the link below (it can be used once):\n\n\
+             {url}\n\n\
+             If you did not request a password reset, you can ignore this mail."
+        );
+        mail::send_mail(Mail::new(email, "Password reset", body));
+    } else {
+        debug!("{:<12} - no user (or email) for the pwd reset", "HANDLER");
+    }
+
+    Ok(success_body())
+}
+
+#[derive(Debug, Deserialize)]
+struct PwdResetRequestPayload {
+    username: String,
+}
+
+fn success_body() -> Json<Value> {
+    Json(json!({
+        "result": {
+            "success": true
+        }
+    }))
+}
+
+/// Returns true (and starts the cooldown) when no reset was requested for the
+/// `username` in the last `PWD_RESET_COOLDOWN_SEC`.
+///
+/// Note: Tracked whether or not the username exists, so that the cooldown
+///       does not tell them apart. In memory, so per web-server instance.
+fn cooldown_take(username: &str) -> bool {
+    static REQUESTED: OnceLock<Mutex<HashMap<String, Instant>>> = OnceLock::new();
+
+    let now = Instant::now();
+    let cooldown = Duration::from_secs(web_config().PWD_RESET_COOLDOWN_SEC);
+    let mut requested = REQUESTED
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .unwrap_or_else(|ex| ex.into_inner());
+
+    if requested.len() > COOLDOWN_PRUNE_THRESHOLD {
+        requested.retain(|_, last| now.duration_since(*last) < cooldown);
+    }
+
+    let key = username.to_lowercase();
+    match requested.get(&key) {
+        Some(last) if now.duration_since(*last) < cooldown => false,
+        _ => {
+            requested.insert(key, now);
+            true
+        }
+    }
+}
+// endregion: --- Reset Request
+
+// region:    --- Reset
+async fn api_pwd_reset_handler(
+    State(mm): State<ModelManager>,
+    Json(payload): Json<PwdResetPayload>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_pwd_reset_handler", "HANDLER");
+
+    let PwdResetPayload { token, pwd_clear } = payload;
+    let root_ctx = Ctx::root_ctx();
+
+    // -- Validate the token.
+    let token: Token = token.parse().map_err(|_| Error::PwdResetTokenInvalid)?;
+    let user: UserForPwdReset =
+        UserBmc::first_by_username(&root_ctx, &mm, &token.ident)
+            .await?
+            .ok_or(Error::PwdResetTokenInvalid)?;
+    validate_pwd_reset_token(&token, user.token_salt)
+        .map_err(|_| Error::PwdResetTokenInvalid)?;
+
+    // -- Reset the password (which rotates the token salt).
+    UserBmc::reset_pwd(&root_ctx, &mm, user.id, user.token_salt, &pwd_clear)
+        .await
+        .map_err(|ex| match ex {
+            model::Error::TokenSaltRotated { .. } => Error::PwdResetTokenInvalid,
+            ex => Error::Model(ex),
+        })?;
+
+    // Create the success body.
+    let body = Json(json!({
+        "result": {
+            "success": true
+        }
+    }));
+
+    Ok(body)
+}
+
+#[derive(Debug, Deserialize)]
+struct PwdResetPayload {
+    token: String,
+    pwd_clear: String,
+}
+// endregion: --- Reset
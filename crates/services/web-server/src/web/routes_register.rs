@@ -0,0 +1,48 @@
+use crate::web::{Error, Result};
+use crate::web_config;
+use axum::extract::State;
+use axum::routing::post;
+use axum::{Json, Router};
+use lib_core::ctx::Ctx;
+use lib_core::model::user::{UserBmc, UserForCreate};
+use lib_core::model::ModelManager;
+use serde_json::{json, Value};
+use tracing::debug;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/api/register", post(api_register_handler))
+        .with_state(mm)
+}
+
+/// Self-service registration (when `REGISTRATION_ENABLED`).
+///
+/// Note: The payload is the `UserForCreate` (i.e., `username` and `pwd_clear`),
+///       so that its validation errors match the payload fields.
+///
+/// Note: Does not log in the new user, which then goes through `/api/login`.
+async fn api_register_handler(
+    State(mm): State<ModelManager>,
+    Json(user_c): Json<UserForCreate>,
+) -> Result<Json<Value>> {
+    debug!("{:<12} - api_register_handler", "HANDLER");
+
+    if !web_config().REGISTRATION_ENABLED {
+        return Err(Error::RegistrationDisabled);
+    }
+
+    let root_ctx = Ctx::root_ctx();
+
+    // Note: The username and password rules are validated by `UserBmc::create`.
+    let user_id = UserBmc::create(&root_ctx, &mm, user_c).await?;
+
+    // Create the success body.
+    let body = Json(json!({
+        "result": {
+            "success": true,
+            "user_id": user_id
+        }
+    }));
+
+    Ok(body)
+}
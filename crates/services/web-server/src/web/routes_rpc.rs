@@ -1,3 +1,5 @@
+use crate::web::client_ip::ClientIp;
+use crate::web::login_guard;
 use crate::web::mw_auth::CtxW;
 use crate::web::Result;
 use axum::extract::State;
@@ -5,9 +7,11 @@ use axum::response::{IntoResponse, Response};
 use axum::routing::post;
 use axum::{Json, Router};
 use lib_core::ctx::Ctx;
-use lib_core::model::ModelManager;
-use lib_rpc::{exec_rpc, RpcRequest};
+use lib_core::model::user::{User, UserBmc};
+use lib_core::model::{self, ModelManager};
+use lib_rpc::{exec_rpc, RpcRequest, PWD_CHECK_METHODS};
 use serde_json::{json, Value};
+use std::net::IpAddr;
 use tracing::debug;
 
 pub fn routes(mm: ModelManager) -> Router {
@@ -27,6 +31,7 @@ pub struct RpcInfo {
 async fn rpc_handler(
     State(mm): State<ModelManager>,
     ctx: CtxW,
+    ClientIp(client_ip): ClientIp,
     Json(rpc_req): Json<RpcRequest>,
 ) -> Response {
     let ctx = ctx.0;
@@ -37,7 +42,9 @@ async fn rpc_handler(
     };
 
     // -- Exec & Store RpcInfo in response.
-    let mut res = _rpc_handler(ctx, mm, rpc_req).await.into_response();
+    let mut res = _rpc_handler(ctx, mm, client_ip, rpc_req)
+        .await
+        .into_response();
     res.extensions_mut().insert(rpc_info);
 
     res
@@ -46,6 +53,7 @@ async fn rpc_handler(
 async fn _rpc_handler(
     ctx: Ctx,
     mm: ModelManager,
+    client_ip: Option<IpAddr>,
     rpc_req: RpcRequest,
 ) -> Result<Json<Value>> {
     let rpc_method = rpc_req.method.clone();
@@ -53,7 +61,11 @@ async fn _rpc_handler(
 
     debug!("{:<12} - _rpc_handler - method: {rpc_method}", "HANDLER");
 
-    let result = exec_rpc(ctx, mm, rpc_req).await?;
+    let result = if PWD_CHECK_METHODS.contains(&rpc_method.as_str()) {
+        exec_rpc_pwd_checked(ctx, mm, client_ip, rpc_req).await?
+    } else {
+        exec_rpc(ctx, mm, rpc_req).await?
+    };
 
     let body_response = json!({
 		"id": rpc_id,
@@ -62,3 +74,111 @@ async fn _rpc_handler(
 
     Ok(Json(body_response))
 }
+
+/// Exec a method checking the current password, guarded as the logins are
+/// (i.e., a session alone, such as a stolen one, cannot guess the password
+/// faster than the login would).
+///
+/// Note: The failures are counted on the user's username (and the client ip),
+///       so shared with the login failures.
+async fn exec_rpc_pwd_checked(
+    ctx: Ctx,
+    mm: ModelManager,
+    client_ip: Option<IpAddr>,
+    rpc_req: RpcRequest,
+) -> Result<Value> {
+    let user: User = UserBmc::get(&ctx, &mm, ctx.user_id()).await?;
+
+    // -- Reject when locked out, and delay after the previous failures.
+    let delay = login_guard::check(&user.username, client_ip)?;
+    tokio::time::sleep(delay).await;
+
+    let res = exec_rpc(ctx, mm, rpc_req).await;
+
+    match &res {
+        Ok(_) => login_guard::record_success(&user.username),
+        Err(lib_rpc::Error::Model(model::Error::PwdNotMatching { .. })) => {
+            login_guard::record_failure(&user.username, client_ip)?
+        }
+        Err(_) => (),
+    }
+
+    Ok(res?)
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::Error;
+    use crate::web_config;
+    use anyhow::{Context, Result};
+    use lib_core::_dev_utils;
+    use lib_core::model::user::UserForCreate;
+
+    #[tokio::test]
+    async fn test_exec_rpc_pwd_checked_err_locked() -> Result<()> {
+        // -- Setup & Fixtures
+        let mm = _dev_utils::init_test().await;
+        let fx_pwd_clear = "Test_exec_rpc_pwd_checked pwd";
+        let fx_max_failures = web_config().LOGIN_MAX_FAILURES;
+        let id = UserBmc::create(
+            &Ctx::root_ctx(),
+            &mm,
+            UserForCreate {
+                username: "test_exec_rpc_pwd_checked_err_locked-user".to_string(),
+                pwd_clear: fx_pwd_clear.to_string(),
+            },
+        )
+        .await?;
+        let ctx = Ctx::new(id)?;
+        let fx_rpc_req = |pwd_current: &str| RpcRequest {
+            id: None,
+            method: "change_email".to_string(),
+            params: Some(json!({
+                "pwd_current": pwd_current,
+                "email": "test_exec_rpc_pwd_checked@localhost",
+            })),
+        };
+
+        // -- Exec
+        let mut results = Vec::new();
+        for _ in 0..fx_max_failures {
+            let rpc_req = fx_rpc_req("Wrong pwd");
+            results.push(
+                exec_rpc_pwd_checked(ctx.clone(), mm.clone(), None, rpc_req).await,
+            );
+        }
+        // Locked, even with the right password.
+        let rpc_req = fx_rpc_req(fx_pwd_clear);
+        let res_locked = exec_rpc_pwd_checked(ctx, mm, None, rpc_req).await;
+
+        // -- Check
+        let (res_last, res_wrongs) =
+            results.split_last().context("Should have results")?;
+        for res in res_wrongs {
+            assert!(
+                matches!(
+                    res,
+                    Err(Error::Rpc(lib_rpc::Error::Model(
+                        model::Error::PwdNotMatching { .. }
+                    )))
+                ),
+                "Should have matched `PwdNotMatching` but was `{res:?}`"
+            );
+        }
+        assert!(
+            matches!(res_last, Err(Error::LoginLocked { is_new: true, .. })),
+            "Should have started the lockout but was `{res_last:?}`"
+        );
+        assert!(
+            matches!(res_locked, Err(Error::LoginLocked { is_new: false, .. })),
+            "Should have been locked but was `{res_locked:?}`"
+        );
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
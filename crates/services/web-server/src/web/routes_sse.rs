@@ -0,0 +1,195 @@
+//! Server-Sent Events feed of the task changes.
+//!
+//! `GET /api/sse/tasks?filters=<json>`, where the optional `filters` are the same
+//! `TaskFilter`s (one or many) as the `list_tasks` rpc method.
+//!
+//! - Each change is sent as a `created`, `updated` or `deleted` event, with the
+//!   `ModelChange` json as data (entity, id, kind, and new state).
+//! - Created/updated tasks are only sent when they match the filters (matched
+//!   in memory, on the change new state). Deleted tasks are always sent, since
+//!   there is no state left to match.
+//! - A `lagged` event is sent when the client is too slow and missed changes
+//!   (the client should then refresh with `list_tasks`).
+//! - The session is re-checked (see `mw_auth::check_ctx_auth`) before each
+//!   event and every `SESSION_CHECK_EVERY`. Once the user is disabled, its token
+//!   salt rotated (e.g., pwd change), or its impersonation ended, a
+//!   `session_ended` event is sent and the stream ends.
+
+use crate::web::mw_auth::{check_ctx_auth, CtxAuth, CtxW};
+use crate::web::{Error, Result};
+use axum::extract::{Extension, Query, State};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::routing::get;
+use axum::Router;
+use futures_util::stream::{self, Stream};
+use lib_core::ctx::Ctx;
+use lib_core::model::change::{ChangeKind, ModelChange};
+use lib_core::model::task::{Task, TaskFilter};
+use lib_core::model::ModelManager;
+use serde::Deserialize;
+use serde_json::Value;
+use std::convert::Infallible;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::broadcast::Receiver;
+use tokio::time::{interval_at, Instant, Interval, MissedTickBehavior};
+use tracing::debug;
+
+/// Session re-check interval, for the feeds without events.
+const SESSION_CHECK_EVERY: Duration = Duration::from_secs(30);
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new()
+        .route("/sse/tasks", get(sse_tasks_handler))
+        .with_state(mm)
+}
+
+#[derive(Deserialize)]
+struct SseTasksQuery {
+    filters: Option<String>,
+}
+
+/// The state of a task change feed.
+struct SseTasksState {
+    changes: Receiver<ModelChange>,
+    filters: Option<Vec<TaskFilter>>,
+    ctx: Ctx,
+    ctx_auth: CtxAuth,
+    mm: ModelManager,
+    session_check: Interval,
+    ended: bool,
+}
+
+async fn sse_tasks_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    Extension(ctx_auth): Extension<CtxAuth>,
+    Query(query): Query<SseTasksQuery>,
+) -> Result<Sse<impl Stream<Item = core::result::Result<Event, Infallible>>>> {
+    debug!("{:<12} - sse_tasks_handler", "HANDLER");
+
+    let filters = query.filters.as_deref().map(parse_filters).transpose()?;
+
+    let mut session_check =
+        interval_at(Instant::now() + SESSION_CHECK_EVERY, SESSION_CHECK_EVERY);
+    session_check.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let state = SseTasksState {
+        changes: mm.subscribe_changes(),
+        filters,
+        ctx: ctx.0,
+        ctx_auth,
+        mm,
+        session_check,
+        ended: false,
+    };
+    let stream = stream::unfold(state, |mut state| async move {
+        if state.ended {
+            return None;
+        }
+
+        let event = next_event(&mut state).await?;
+        Some((Ok(event), state))
+    });
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
+/// The next event of the feed (`None` when the change feed is closed).
+async fn next_event(state: &mut SseTasksState) -> Option<Event> {
+    loop {
+        let event = tokio::select! {
+            change = state.changes.recv() => match change {
+                Ok(change) => {
+                    if !is_task_change_matching(&change, state.filters.as_deref()) {
+                        continue;
+                    }
+                    change_event(&change)
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    Event::default().event("lagged").data(missed.to_string())
+                }
+                Err(RecvError::Closed) => return None,
+            },
+
+            _ = state.session_check.tick() => {
+                match session_ended_event(state).await {
+                    Some(event) => return Some(event),
+                    None => continue,
+                }
+            }
+        };
+
+        // Note: Checked before each event, so no event is sent once ended.
+        if let Some(ended_event) = session_ended_event(state).await {
+            return Some(ended_event);
+        }
+        return Some(event);
+    }
+}
+
+/// Re-check the session, returning the `session_ended` event (and marking the
+/// feed as ended) when it no longer holds.
+async fn session_ended_event(state: &mut SseTasksState) -> Option<Event> {
+    let Err(ex) = check_ctx_auth(&state.mm, &state.ctx, &state.ctx_auth).await
+    else {
+        return None;
+    };
+
+    debug!("{:<12} - sse session ended - {ex:?}", "SSE");
+    state.ended = true;
+    Some(Event::default().event("session_ended").data(""))
+}
+
+/// Parse the `filters` query param (json object or array) into the
+/// `TaskFilter`s.
+fn parse_filters(filters: &str) -> Result<Vec<TaskFilter>> {
+    let to_error = |ex: serde_json::Error| Error::SseFailJsonFilters {
+        cause: ex.to_string(),
+    };
+
+    let filters: Value = serde_json::from_str(filters).map_err(to_error)?;
+    let filters = match filters {
+        Value::Array(_) => filters,
+        filter => Value::Array(vec![filter]),
+    };
+
+    serde_json::from_value(filters).map_err(to_error)
+}
+
+/// Returns true if the task change matches any of the `filters`.
+///
+/// Note: Matched on the change new state, so no db query per change.
+fn is_task_change_matching(
+    change: &ModelChange,
+    filters: Option<&[TaskFilter]>,
+) -> bool {
+    if change.entity != "task" {
+        return false;
+    }
+
+    match (change.kind, filters) {
+        (ChangeKind::Deleted, _) | (_, None) => true,
+        (ChangeKind::Created | ChangeKind::Updated, Some(filters)) => {
+            let Some(Ok(task)) =
+                change.data.clone().map(serde_json::from_value::<Task>)
+            else {
+                return false;
+            };
+            filters.iter().any(|filter| filter.matches(&task))
+        }
+    }
+}
+
+fn change_event(change: &ModelChange) -> Event {
+    let event_name = match change.kind {
+        ChangeKind::Created => "created",
+        ChangeKind::Updated => "updated",
+        ChangeKind::Deleted => "deleted",
+    };
+
+    // Note: ModelChange json serialization cannot fail.
+    Event::default()
+        .event(event_name)
+        .json_data(change)
+        .unwrap_or_default()
+}
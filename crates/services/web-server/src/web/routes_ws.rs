@@ -0,0 +1,219 @@
+//! JSON-RPC over WebSocket.
+//!
+//! - The socket is authenticated once, at upgrade time, by the same
+//!   `mw_ctx_resolve` / `mw_ctx_require` as `/api/rpc`, and its upgrade
+//!   request origin is checked by `mw_csrf_check`.
+//! - The session is then re-checked (see `mw_auth::check_ctx_auth`) before each
+//!   rpc request and at each ping, and the socket is closed once the user is
+//!   disabled, its token salt rotated (e.g., pwd change), or its impersonation
+//!   ended.
+//! - Each text message is a `RpcRequest`, executed concurrently through
+//!   `lib_rpc::exec_rpc`. Responses are sent as soon as they are ready, so the
+//!   client must match them by their rpc `id`.
+//! - A ping is sent every `WS_PING_INTERVAL_SEC`. The socket is closed when the
+//!   client does not answer for two ping intervals, or when it does not send any
+//!   rpc request for `WS_IDLE_TIMEOUT_SEC`.
+//! - Each rpc call is rate limited, as the `/api/rpc` ones (see `rate_limit`).
+
+use crate::log::log_request;
+use crate::metrics;
+use crate::rate_limit::{self, RateKey};
+use crate::web::mw_auth::{check_ctx_auth, CtxAuth, CtxW};
+use crate::web::mw_res_map::client_error_body;
+use crate::web::routes_rpc::RpcInfo;
+use crate::web::Error;
+use crate::web_config;
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
+use axum::extract::{Extension, OriginalUri, State, WebSocketUpgrade};
+use axum::http::{Method, Uri};
+use axum::response::Response;
+use axum::routing::get;
+use axum::Router;
+use futures_util::{SinkExt, StreamExt};
+use lib_core::ctx::Ctx;
+use lib_core::model::ModelManager;
+use lib_rpc::{exec_rpc, RpcRequest};
+use serde_json::{json, Value};
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
+use tracing::debug;
+use uuid::Uuid;
+
+pub fn routes(mm: ModelManager) -> Router {
+    Router::new().route("/ws", get(ws_handler)).with_state(mm)
+}
+
+async fn ws_handler(
+    State(mm): State<ModelManager>,
+    ctx: CtxW,
+    Extension(ctx_auth): Extension<CtxAuth>,
+    OriginalUri(uri): OriginalUri,
+    ws: WebSocketUpgrade,
+) -> Response {
+    debug!("{:<12} - ws_handler", "HANDLER");
+
+    ws.on_upgrade(move |socket| handle_socket(socket, ctx.0, ctx_auth, mm, uri))
+}
+
+/// Close of a session which no longer holds (see `check_ctx_auth`).
+///
+/// Note: The rpcs still in flight are aborted (see below).
+const SESSION_ENDED: (u16, &str) = (close_code::POLICY, "session ended");
+
+async fn handle_socket(
+    socket: WebSocket,
+    ctx: Ctx,
+    ctx_auth: CtxAuth,
+    mm: ModelManager,
+    uri: Uri,
+) {
+    let config = web_config();
+    let ping_every = Duration::from_secs(config.WS_PING_INTERVAL_SEC);
+    let idle_timeout = Duration::from_secs(config.WS_IDLE_TIMEOUT_SEC);
+
+    let (mut ws_tx, mut ws_rx) = socket.split();
+    let mut in_flight: JoinSet<Value> = JoinSet::new();
+
+    let mut ping_interval = interval_at(Instant::now() + ping_every, ping_every);
+    ping_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut last_received = Instant::now();
+    let mut last_request = Instant::now();
+
+    let close_reason = loop {
+        tokio::select! {
+            // -- Client messages.
+            msg = ws_rx.next() => {
+                let Some(Ok(msg)) = msg else {
+                    break None;
+                };
+                last_received = Instant::now();
+
+                match msg {
+                    Message::Text(text) => {
+                        if let Err(ex) = check_ctx_auth(&mm, &ctx, &ctx_auth).await {
+                            debug!("{:<12} - ws session ended - {ex:?}", "WS");
+                            break Some(SESSION_ENDED);
+                        }
+                        last_request = Instant::now();
+                        let (ctx, mm, uri) = (ctx.clone(), mm.clone(), uri.clone());
+                        in_flight.spawn(exec_ws_rpc(ctx, mm, uri, text));
+                    }
+                    Message::Close(_) => break None,
+                    // Note: Pings are answered by axum, and pongs only need
+                    //       to update `last_received`.
+                    Message::Ping(_) | Message::Pong(_) | Message::Binary(_) => (),
+                }
+            }
+
+            // -- Rpc responses, in completion order.
+            Some(res) = in_flight.join_next(), if !in_flight.is_empty() => {
+                let Ok(res_json) = res else {
+                    continue;
+                };
+                if ws_tx.send(Message::Text(res_json.to_string())).await.is_err() {
+                    break None;
+                }
+            }
+
+            // -- Heartbeat and idle checks.
+            _ = ping_interval.tick() => {
+                if last_received.elapsed() > ping_every * 2 {
+                    break Some((close_code::AWAY, "no pong"));
+                }
+                if in_flight.is_empty() && last_request.elapsed() > idle_timeout {
+                    break Some((close_code::AWAY, "idle timeout"));
+                }
+                if let Err(ex) = check_ctx_auth(&mm, &ctx, &ctx_auth).await {
+                    debug!("{:<12} - ws session ended - {ex:?}", "WS");
+                    break Some(SESSION_ENDED);
+                }
+                if ws_tx.send(Message::Ping(Vec::new())).await.is_err() {
+                    break None;
+                }
+            }
+        }
+    };
+
+    debug!("{:<12} - ws closed - {close_reason:?}", "WS");
+
+    if let Some((code, reason)) = close_reason {
+        let close_frame = CloseFrame {
+            code,
+            reason: reason.into(),
+        };
+        let _ = ws_tx.send(Message::Close(Some(close_frame))).await;
+    }
+
+    // Dropping the JoinSet aborts the rpc calls still in flight.
+}
+
+/// Execute one websocket rpc message, and return the json-rpc response
+/// (result or error envelope).
+///
+/// Note: Each rpc call gets its own request log line.
+async fn exec_ws_rpc(ctx: Ctx, mm: ModelManager, uri: Uri, text: String) -> Value {
+    let uuid = Uuid::new_v4();
+    let start = Instant::now();
+    // Note: Each rpc call gets its own request id (rather than the upgrade one).
+    let ctx = ctx.with_req_id(uuid);
+
+    let rpc_req = serde_json::from_str::<RpcRequest>(&text).map_err(|ex| {
+        Error::RpcFailJsonRequest {
+            cause: ex.to_string(),
+        }
+    });
+    let rpc_info = rpc_req.as_ref().ok().map(|rpc_req| RpcInfo {
+        id: rpc_req.id.clone(),
+        method: rpc_req.method.clone(),
+    });
+    let rpc_id = rpc_info.as_ref().and_then(|rpc| rpc.id.clone());
+
+    let result = match rpc_req {
+        Ok(rpc_req) => {
+            debug!(
+                "{:<12} - exec_ws_rpc - method: {}",
+                "HANDLER", rpc_req.method
+            );
+            let rate_key = RateKey::User(ctx.user_id());
+            match rate_limit::check(rate_key, Some(&rpc_req.method)).await {
+                Some(retry_after) => Err(Error::RateLimited {
+                    rpc_method: Some(rpc_req.method.clone()),
+                    retry_after_sec: retry_after.as_secs() + 1,
+                }),
+                None => exec_rpc(ctx.clone(), mm, rpc_req)
+                    .await
+                    .map_err(Error::from),
+            }
+        }
+        Err(web_error) => Err(web_error),
+    };
+
+    if let Some(rpc_info) = &rpc_info {
+        metrics::observe_rpc(&rpc_info.method, result.is_ok(), start.elapsed());
+    }
+
+    let (res_json, web_error, client_error) = match result {
+        Ok(result) => (json!({ "id": rpc_id, "result": result }), None, None),
+        Err(web_error) => {
+            let (_, client_error) = web_error.client_status_and_error();
+            metrics::observe_error(&web_error, &client_error);
+            let body = client_error_body(rpc_id, &client_error, uuid);
+            (body, Some(web_error), Some(client_error))
+        }
+    };
+
+    let _ = log_request(
+        uuid,
+        Method::GET,
+        uri,
+        start.elapsed(),
+        rpc_info.as_ref(),
+        Some(ctx),
+        web_error.as_ref(),
+        client_error,
+    )
+    .await;
+
+    res_json
+}
@@ -5,15 +5,42 @@
 CREATE TABLE "user" (
                         id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
                         username varchar(128) NOT NULL UNIQUE,
+                        email varchar(256), -- e.g., pwd reset mails
+    -- PROFILE (editable by the user)
+                        display_name varchar(128),
+                        timezone varchar(64), -- IANA name (e.g., Europe/Paris)
+                        locale varchar(35), -- BCP 47 tag (e.g., en-US)
     -- AUTH
                         pwd varchar(256),
                         pwd_salt uuid NOT NULL DEFAULT gen_random_uuid(),
-                        token_salt uuid NOT NULL DEFAULT gen_random_uuid()
+                        token_salt uuid NOT NULL DEFAULT gen_random_uuid(),
+    -- ACCESS
+                        is_admin bool NOT NULL DEFAULT false, -- e.g., user management rpcs
+                        disabled bool NOT NULL DEFAULT false, -- no login, no session
+                        impersonating_user_id BIGINT REFERENCES "user"(id) ON DELETE SET NULL, -- on the admin row, the user it acts as
+    -- 2FA (totp)
+                        totp_secret_enc varchar(256),
+                        totp_enabled bool NOT NULL DEFAULT false,
+                        totp_last_step BIGINT NOT NULL DEFAULT 0,
+                        totp_recovery_codes text,
+    -- SSO (oidc), the external subject of the user
+                        oidc_issuer varchar(256),
+                        oidc_subject varchar(256),
+    -- AUDIT (last write), by the ctx user, and the admin acting as it if any
+                        mid BIGINT,
+                        mimpersonator_id BIGINT,
+                        mtime timestamp with time zone,
+
+                        UNIQUE (oidc_issuer, oidc_subject)
 
 );
 
 CREATE TABLE "task" (
                         id BIGINT GENERATED BY DEFAULT AS IDENTITY (START WITH 1000) PRIMARY KEY,
                         title varchar(128) NOT NULL UNIQUE,
-                            done bool NOT NULL DEFAULT false
+                            done bool NOT NULL DEFAULT false,
+    -- AUDIT (last write), by the ctx user, and the admin acting as it if any
+                        mid BIGINT,
+                        mimpersonator_id BIGINT,
+                        mtime timestamp with time zone
 );
\ No newline at end of file
@@ -2,5 +2,5 @@
 
 -- User demo1
 INSERT INTO "user"
-(username) VALUES('demo1');
+(username, email, is_admin) VALUES('demo1', 'demo1@localhost', true);
 